@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+
+use libdictdefinition::normalize::{fold_diacritics, fold_romanization, normalize_key};
+use libdictdefinition::{DictionaryElementData, HyperlinkedText, BACKLINK_CAP};
+
+use Languages::TargetLanguage;
+
+/// Maps folded romanizations to the native-script keys they belong to, so the server can find
+/// "привет" when the user types "privet".
+pub fn build_romanization_index(
+    dictionary_data: &[DictionaryElementData],
+) -> HashMap<(TargetLanguage, String), Vec<String>> {
+    let mut index: HashMap<(TargetLanguage, String), Vec<String>> = HashMap::new();
+
+    for element in dictionary_data {
+        for romanization in &element.romanizations {
+            let folded = fold_romanization(romanization);
+            if folded.is_empty() || folded == element.key.to_lowercase() {
+                continue;
+            }
+
+            let keys = index.entry((element.lang.clone(), folded)).or_default();
+            if !keys.contains(&element.key) {
+                keys.push(element.key.clone());
+            }
+        }
+    }
+
+    // Element order comes out of a HashMap in phase 3; sort so candidates are stable across builds.
+    for keys in index.values_mut() {
+        keys.sort();
+    }
+
+    index
+}
+
+/// Maps diacritic-folded keys to the keys they came from, so "deja" finds "déjà". Keys that folding
+/// changes only by case or normalisation are already found by `PerfectHashIndex::variants` and skipped, which
+/// leaves nothing for languages without optional accents.
+pub fn build_folding_index(
+    dictionary_data: &[DictionaryElementData],
+) -> HashMap<(TargetLanguage, String), Vec<String>> {
+    let mut index: HashMap<(TargetLanguage, String), Vec<String>> = HashMap::new();
+
+    for element in dictionary_data {
+        let folded = fold_diacritics(&element.lang, &element.key);
+        if folded == normalize_key(&element.key) {
+            continue;
+        }
+
+        index
+            .entry((element.lang.clone(), folded))
+            .or_default()
+            .push(element.key.clone());
+    }
+
+    for keys in index.values_mut() {
+        keys.sort();
+    }
+
+    index
+}
+
+/// Reverse of the gloss link graph: for each key, the entries whose definitions link to it.
+/// Form-of entries that dereference to the word rank first (all the "plural of X" entries), then
+/// entries by how early in their definitions the link appears, then shorter keys.
+pub fn build_backlink_index(
+    dictionary_data: &[DictionaryElementData],
+) -> HashMap<(TargetLanguage, String), Vec<String>> {
+    // (not form-of, index of first linking definition, source key); sorts best first
+    type Rank<'a> = (bool, usize, &'a String);
+    let mut ranked: HashMap<(TargetLanguage, String), Vec<Rank>> = HashMap::new();
+
+    for element in dictionary_data {
+        let mut seen_targets: Vec<&String> = Vec::new();
+
+        for (def_index, definition) in element.definitions.iter().enumerate() {
+            for item in &definition.text {
+                let target = match item {
+                    HyperlinkedText::Link { target, .. } => target,
+                    HyperlinkedText::Plain(_) => continue,
+                };
+                if *target == element.key || seen_targets.contains(&target) {
+                    continue;
+                }
+                seen_targets.push(target);
+
+                let is_form_of = element
+                    .lemma_reference
+                    .as_ref()
+                    .is_some_and(|r| r.key == *target);
+
+                ranked
+                    .entry((element.lang.clone(), target.clone()))
+                    .or_default()
+                    .push((!is_form_of, def_index, &element.key));
+            }
+        }
+    }
+
+    ranked
+        .into_iter()
+        .map(|(target, mut sources)| {
+            sources.sort_by(|a, b| {
+                (a.0, a.1, a.2.chars().count(), a.2).cmp(&(b.0, b.1, b.2.chars().count(), b.2))
+            });
+            sources.truncate(BACKLINK_CAP);
+            let keys = sources.into_iter().map(|(_, _, key)| key.clone()).collect();
+            (target, keys)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libdictdefinition::{Definition, LemmaReference};
+
+    fn element(key: &str, romanizations: &[&str]) -> DictionaryElementData {
+        DictionaryElementData {
+            romanizations: romanizations.iter().map(|s| s.to_string()).collect(),
+            ..DictionaryElementData::new(TargetLanguage::Russian, key)
+        }
+    }
+
+    #[test]
+    fn test_romanization_index_folds_forms() {
+        let index = build_romanization_index(&[element("привет", &["privét", "privet"])]);
+
+        assert_eq!(
+            index.get(&(TargetLanguage::Russian, "privet".to_string())),
+            Some(&vec!["привет".to_string()])
+        );
+        assert_eq!(index.len(), 1);
+    }
+
+    #[test]
+    fn test_backlinks_rank_form_of_first() {
+        let definition = |text: Vec<HyperlinkedText>| Definition {
+            text,
+            tags: Vec::new(),
+        };
+
+        let mut see_also = element("дома", &[]);
+        see_also.definitions = vec![definition(vec![HyperlinkedText::link("дом")])];
+
+        let mut plural = element("домы", &[]);
+        plural.definitions = vec![
+            definition(vec![HyperlinkedText::Plain("old".to_string())]),
+            definition(vec![HyperlinkedText::link("дом")]),
+        ];
+        plural.lemma_reference = Some(LemmaReference {
+            lang: TargetLanguage::Russian,
+            key: "дом".to_string(),
+        });
+
+        let index = build_backlink_index(&[see_also, plural, element("дом", &[])]);
+
+        assert_eq!(
+            index.get(&(TargetLanguage::Russian, "дом".to_string())),
+            Some(&vec!["домы".to_string(), "дома".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_folding_index_skips_unchanged_keys() {
+        let index = build_folding_index(&[
+            element("привет", &[]),
+            element("Ёлка", &[]),
+            DictionaryElementData::new(TargetLanguage::French, "Déjà"),
+            DictionaryElementData::new(TargetLanguage::German, "Müll"),
+        ]);
+
+        assert_eq!(
+            index.get(&(TargetLanguage::French, "deja".to_string())),
+            Some(&vec!["Déjà".to_string()])
+        );
+        assert_eq!(
+            index.get(&(TargetLanguage::Russian, "елка".to_string())),
+            Some(&vec!["Ёлка".to_string()])
+        );
+        assert_eq!(index.len(), 2);
+    }
+}
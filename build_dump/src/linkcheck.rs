@@ -0,0 +1,112 @@
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+use libdictdefinition::{DictionaryElementData, HyperlinkedText};
+
+use Languages::TargetLanguage;
+
+/// Links are made against the phase-1 word set, but phase 2 drops entries it can't parse, so
+/// some targets never make it into the dump. Demotes every link whose target wasn't emitted to
+/// plain text and reports how many were dropped per language.
+pub fn check_link_integrity(
+    mut dictionary_data: Vec<DictionaryElementData>,
+) -> Vec<DictionaryElementData> {
+    let emitted: HashSet<(String, TargetLanguage)> = dictionary_data
+        .iter()
+        .map(|e| (e.key.clone(), e.lang.clone()))
+        .collect();
+
+    let counts: Vec<(TargetLanguage, usize, usize)> = dictionary_data
+        .par_iter_mut()
+        .map(|element| {
+            let (checked, demoted) = demote_dangling_links(element, &emitted);
+            (element.lang.clone(), checked, demoted)
+        })
+        .collect();
+
+    let mut per_language: HashMap<TargetLanguage, (usize, usize)> = HashMap::new();
+    for (lang, checked, demoted) in counts {
+        let totals = per_language.entry(lang).or_default();
+        totals.0 += checked;
+        totals.1 += demoted;
+    }
+
+    for (lang, (checked, demoted)) in &per_language {
+        println!(
+            "Link check {:?}: {} links, {} dangling demoted to plain text",
+            lang, checked, demoted
+        );
+    }
+
+    dictionary_data
+}
+
+/// Returns (links checked, links demoted).
+fn demote_dangling_links(
+    element: &mut DictionaryElementData,
+    emitted: &HashSet<(String, TargetLanguage)>,
+) -> (usize, usize) {
+    let mut checked = 0;
+    let mut demoted = 0;
+
+    for definition in &mut element.definitions {
+        for item in &mut definition.text {
+            if let HyperlinkedText::Link { text, target } = item {
+                checked += 1;
+                if !emitted.contains(&(target.clone(), element.lang.clone())) {
+                    *item = HyperlinkedText::Plain(std::mem::take(text));
+                    demoted += 1;
+                }
+            }
+        }
+    }
+
+    (checked, demoted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libdictdefinition::Definition;
+
+    fn element(key: &str, text: Vec<HyperlinkedText>) -> DictionaryElementData {
+        DictionaryElementData {
+            definitions: vec![Definition {
+                text,
+                tags: Vec::new(),
+            }],
+            ..DictionaryElementData::new(TargetLanguage::German, key)
+        }
+    }
+
+    #[test]
+    fn test_dangling_links_demoted() {
+        let data = vec![
+            element(
+                "Haus",
+                vec![
+                    HyperlinkedText::link("Haus"),
+                    HyperlinkedText::link("Gebäude"),
+                ],
+            ),
+            element("Gebäude", Vec::new()),
+            element("Hof", vec![HyperlinkedText::link("Hütte")]),
+        ];
+
+        let checked = check_link_integrity(data);
+        let hof = checked.iter().find(|e| e.key == "Hof").unwrap();
+        let haus = checked.iter().find(|e| e.key == "Haus").unwrap();
+
+        assert_eq!(
+            hof.definitions[0].text,
+            vec![HyperlinkedText::Plain("Hütte".to_string())]
+        );
+        assert_eq!(
+            haus.definitions[0].text,
+            vec![
+                HyperlinkedText::link("Haus"),
+                HyperlinkedText::link("Gebäude")
+            ]
+        );
+    }
+}
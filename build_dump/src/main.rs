@@ -5,17 +5,26 @@ use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::path::Path;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use xxhash_rust::xxh3::Xxh3;
 
+mod indexes;
+mod linkcheck;
 mod phase1load;
 mod phase2transform;
 mod phase3dereference;
 mod phase4compress;
 mod phase5dump;
 
-use libdictdefinition::{CompressedDictionaryElementWrapper, Definition, DictionaryElementData};
+use libdictdefinition::index::PerfectHashIndex;
+use libdictdefinition::{
+    CompressedDictionaryElementWrapper, Definition, DictionaryDump, DictionaryElementData,
+};
 
 use Languages::TargetLanguage;
 
+use indexes::{build_backlink_index, build_folding_index, build_romanization_index};
+use linkcheck::check_link_integrity;
 use phase1load::build_word_set;
 use phase2transform::build_dictionary_data;
 use phase3dereference::process_dereferences;
@@ -39,6 +48,9 @@ fn main() -> std::io::Result<()> {
     let dictionary_data = process_dereferences(dictionary_data);
     println!("Phase 3 complete.");
 
+    let dictionary_data = check_link_integrity(dictionary_data);
+    println!("Link check complete.");
+
     output_json_sample(
         &dictionary_data,
         "Haus",
@@ -46,18 +58,69 @@ fn main() -> std::io::Result<()> {
         json_output_path,
     )?;
 
+    let romanizations = build_romanization_index(&dictionary_data);
+    println!("Romanization index built with {} keys", romanizations.len());
+
+    let folded = build_folding_index(&dictionary_data);
+    println!("Folding index built with {} keys", folded.len());
+
+    let backlinks = build_backlink_index(&dictionary_data);
+    println!("Backlink index built with {} keys", backlinks.len());
+
     let compressed_data = compress_dictionary_data(dictionary_data);
     println!(
         "Phase 4 complete. Compressed data size: {}",
         compressed_data.len()
     );
 
-    output_compressed_dict(&compressed_data, output_path)?;
+    let start_t = Instant::now();
+    let elements = PerfectHashIndex::build(compressed_data);
+    println!(
+        "Perfect hash index built in {:.2}s",
+        start_t.elapsed().as_secs_f32()
+    );
+
+    let mut dump = DictionaryDump {
+        elements,
+        romanizations,
+        folded,
+        backlinks,
+        build_id: String::new(),
+        built_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("clock is after 1970")
+            .as_secs(),
+    };
+    dump.build_id = build_id(&dump);
+    println!("Build ID {}", dump.build_id);
+
+    output_compressed_dict(&dump, output_path)?;
     println!("Phase 5. complete. Output written to {:?}", output_path);
 
     Ok(())
 }
 
+/// Digest of everything the server answers from: the entries and every index. Hashed as bincode,
+/// with the indexes' entries sorted, so it is the same for the same data on any machine.
+fn build_id(dump: &DictionaryDump) -> String {
+    let mut hasher = Xxh3::new();
+    for element in dump.elements.iter() {
+        hasher.update(&bincode::serialize(element).expect("entries serialize"));
+    }
+    for index in [&dump.romanizations, &dump.folded, &dump.backlinks] {
+        let mut entries: Vec<Vec<u8>> = index
+            .iter()
+            .map(|entry| bincode::serialize(&entry).expect("indexes serialize"))
+            .collect();
+        entries.sort();
+        hasher.update(&(entries.len() as u64).to_le_bytes());
+        for entry in entries {
+            hasher.update(&entry);
+        }
+    }
+    format!("{:016x}", hasher.digest())
+}
+
 fn output_json_sample(
     dictionary_data: &[DictionaryElementData],
     word: &str,
@@ -7,7 +7,13 @@ use std::io::{BufRead, BufReader};
 use std::path::Path;
 use unicode_normalization::UnicodeNormalization;
 
-use libdictdefinition::{Definition, DictionaryElementData, HyperlinkedText};
+use libdictdefinition::normalize::{lowercase_with_first_uppercase, transliterate};
+use libdictdefinition::segment::{is_scriptio_continua, segment};
+use libdictdefinition::tokenize::tokenize;
+use libdictdefinition::{
+    Definition, DictionaryElementData, GrammaticalAnalysis, HyperlinkedText, InflectedForm,
+    Pronunciation,
+};
 
 use Languages::TargetLanguage;
 
@@ -112,16 +118,29 @@ fn merge_duplicates(
                     if existing.ipa.is_none() {
                         existing.ipa = element.ipa.clone();
                     }
+                    existing
+                        .pronunciations
+                        .extend(element.pronunciations.clone());
+                    merge_pronunciations(&mut existing.pronunciations);
+                    existing.romanizations.extend(element.romanizations.clone());
+                    dedup_preserve_order(&mut existing.romanizations);
                     existing.word_types.extend(element.word_types.clone());
                     dedup_preserve_order(&mut existing.word_types);
                     existing.definitions.extend(element.definitions.clone());
                     consolidate_definitions(&mut existing.definitions);
+                    existing.analysis.extend(element.analysis.clone());
+                    dedup_preserve_order(&mut existing.analysis);
+                    existing.forms.extend(element.forms.clone());
+                    dedup_preserve_order(&mut existing.forms);
                 })
                 .or_insert_with(|| {
                     let mut new_element = element.clone();
                     dedup_preserve_order(&mut new_element.audio);
+                    merge_pronunciations(&mut new_element.pronunciations);
                     dedup_preserve_order(&mut new_element.word_types);
                     consolidate_definitions(&mut new_element.definitions);
+                    dedup_preserve_order(&mut new_element.analysis);
+                    dedup_preserve_order(&mut new_element.forms);
                     new_element
                 });
         }
@@ -157,6 +176,27 @@ fn consolidate_definitions(existing_definitions: &mut Vec<Definition>) {
     *existing_definitions = consolidated;
 }
 
+/// Folds pronunciations with the same IPA and audio into one, keeping every regional tag.
+fn merge_pronunciations(pronunciations: &mut Vec<Pronunciation>) {
+    let mut merged: Vec<Pronunciation> = Vec::new();
+
+    for pronunciation in pronunciations.drain(..) {
+        match merged.iter_mut().find(|p| {
+            p.ipa == pronunciation.ipa
+                && p.ogg_url == pronunciation.ogg_url
+                && p.mp3_url == pronunciation.mp3_url
+        }) {
+            Some(existing) => {
+                existing.tags.extend(pronunciation.tags);
+                dedup_preserve_order(&mut existing.tags);
+            }
+            None => merged.push(pronunciation),
+        }
+    }
+
+    *pronunciations = merged;
+}
+
 fn dedup_preserve_order<T: Eq + std::hash::Hash + Clone>(v: &mut Vec<T>) {
     let mut seen = std::collections::HashSet::new();
     v.retain(|item| seen.insert(item.clone()));
@@ -184,6 +224,10 @@ fn process_json_entry(
     // Parse common data once
     let audio = get_audio(json);
     let ipa = get_ipa(json);
+    let pronunciations = get_pronunciations(json);
+    let romanizations = get_romanizations(json, &word);
+    let analysis = get_analysis(json);
+    let forms = get_forms(json);
     let word_types = match get_word_types(json) {
         Some(wt) => wt,
         None => return Vec::new(),
@@ -207,9 +251,15 @@ fn process_json_entry(
                 lang: lang,
                 audio: audio.clone(),
                 ipa: ipa.clone(),
+                pronunciations: pronunciations.clone(),
+                romanizations: romanizations.clone(),
                 word_types: word_types.clone(),
                 definitions: definitions,
+                analysis: analysis.clone(),
+                forms: forms.clone(),
                 dereferenced_text: None,
+                lemma_reference: None,
+                resolved_lemma: None,
             });
         }
     }
@@ -243,6 +293,112 @@ fn get_ipa(json: &Value) -> Option<String> {
         .map(|s| s.to_string())
 }
 
+fn get_pronunciations(json: &Value) -> Vec<Pronunciation> {
+    json.get("sounds")
+        .and_then(|sounds| sounds.as_array())
+        .map_or(Vec::new(), |sounds| {
+            sounds
+                .iter()
+                .filter_map(|sound| {
+                    let field = |name: &str| {
+                        sound
+                            .get(name)
+                            .and_then(|v| v.as_str())
+                            .map(|s| s.to_string())
+                    };
+                    let pronunciation = Pronunciation {
+                        ipa: field("ipa"),
+                        ogg_url: field("ogg_url"),
+                        mp3_url: field("mp3_url"),
+                        tags: get_raw_tags(sound)
+                            .into_iter()
+                            .map(|s| s.to_string())
+                            .collect(),
+                    };
+
+                    if pronunciation.ipa.is_none()
+                        && pronunciation.ogg_url.is_none()
+                        && pronunciation.mp3_url.is_none()
+                    {
+                        return None;
+                    }
+                    Some(pronunciation)
+                })
+                .collect()
+        })
+}
+
+fn get_romanizations(json: &Value, word: &str) -> Vec<String> {
+    let mut out: Vec<String> =
+        json.get("forms")
+            .and_then(|forms| forms.as_array())
+            .map_or(Vec::new(), |forms| {
+                forms
+                    .iter()
+                    .filter(|form| get_raw_tags(form).contains(&"romanization"))
+                    .filter_map(|form| form.get("form").and_then(|f| f.as_str()))
+                    .map(|s| s.to_string())
+                    .collect()
+            });
+
+    if let Some(transliteration) = transliterate(word) {
+        out.push(transliteration);
+    }
+
+    dedup_preserve_order(&mut out);
+    out
+}
+
+fn get_raw_tags(json: &Value) -> Vec<&str> {
+    json.get("tags")
+        .and_then(|t| t.as_array())
+        .map_or(Vec::new(), |tags| {
+            tags.iter().filter_map(|tag| tag.as_str()).collect()
+        })
+}
+
+fn get_analysis(json: &Value) -> Vec<GrammaticalAnalysis> {
+    let mut out = Vec::new();
+    let senses = match json.get("senses").and_then(|senses| senses.as_array()) {
+        Some(senses) => senses,
+        None => return out,
+    };
+
+    for sense in senses {
+        let tags = get_raw_tags(sense);
+        if !tags.contains(&"form-of") {
+            continue;
+        }
+        if let Some(analysis) = GrammaticalAnalysis::from_tags(&tags) {
+            out.push(analysis);
+        }
+    }
+
+    dedup_preserve_order(&mut out);
+    out
+}
+
+fn get_forms(json: &Value) -> Vec<InflectedForm> {
+    json.get("forms")
+        .and_then(|forms| forms.as_array())
+        .map_or(Vec::new(), |forms| {
+            forms
+                .iter()
+                .filter_map(|form| {
+                    let text = form.get("form").and_then(|f| f.as_str())?;
+                    if text.is_empty() || text == "-" {
+                        return None;
+                    }
+                    let analysis = GrammaticalAnalysis::from_tags(&get_raw_tags(form))?;
+                    Some(InflectedForm {
+                        form: text.to_string(),
+                        analysis,
+                    })
+                })
+                .collect()
+        })
+}
+
 fn get_word_types(json: &Value) -> Option<Vec<String>> {
     json.get("pos")
         .and_then(|pos| pos.as_str())
@@ -366,7 +522,38 @@ const WORD_SET_EXCEPTIONS: [&'static str; 63] = [
 ];
 
 fn remove_diacritics(input: &str) -> String {
-    input.nfd().filter(|&c| c != '\u{0301}').collect::<String>()
+    input
+        .nfd()
+        .filter(|&c| c != '\u{0301}')
+        .nfc()
+        .collect::<String>()
+}
+
+/// The key a gloss word should link to: the word itself, its stress-stripped form (Russian), or a
+/// case variant of either ("The" → "the"), whichever the word set actually contains.
+fn resolve_link_target(
+    word: &str,
+    word_set: &HashSet<(String, TargetLanguage)>,
+    language: &TargetLanguage,
+) -> Option<String> {
+    let mut candidates = vec![word.to_string()];
+    if *language == TargetLanguage::Russian {
+        let stripped = remove_diacritics(word);
+        if stripped != word {
+            candidates.push(stripped);
+        }
+    }
+
+    let case_variants: Vec<String> = candidates
+        .iter()
+        .flat_map(|c| [c.to_lowercase(), lowercase_with_first_uppercase(c)])
+        .collect();
+    candidates.extend(case_variants);
+
+    candidates.into_iter().find(|candidate| {
+        !WORD_SET_EXCEPTIONS.contains(&candidate.as_str())
+            && word_set.contains(&(candidate.clone(), language.clone()))
+    })
 }
 
 pub fn hyperlink_text(
@@ -374,68 +561,45 @@ pub fn hyperlink_text(
     word_set: &HashSet<(String, TargetLanguage)>,
     language: &TargetLanguage,
 ) -> Vec<HyperlinkedText> {
-    let mut result = Vec::new();
-    let mut current_word = String::new();
-    let mut was_last_filler = false;
-
-    fn is_non_content(c: &char) -> bool {
-        let also_prohibited = [
-            '!', '"', '£', '$', '%', '^', '&', '*', '(', ')', '-', '_', '=', '+', '[', ']', ':',
-            ';', '\'', '~', '@', '#', '<', ',', '.', '>', '/', '?', '\\', '|',
-        ];
-        c.is_whitespace() || c.is_numeric() || also_prohibited.contains(c)
-    }
-
     let process_word = |word_str: &str| -> HyperlinkedText {
         if WORD_SET_EXCEPTIONS.contains(&word_str) {
             return HyperlinkedText::Plain(word_str.to_string());
         }
 
-        if word_set.contains(&(word_str.to_string(), language.clone())) {
-            return HyperlinkedText::Link(word_str.to_string());
+        match resolve_link_target(word_str, word_set, language) {
+            Some(target) => HyperlinkedText::Link {
+                text: word_str.to_string(),
+                target,
+            },
+            None => HyperlinkedText::Plain(word_str.to_string()),
         }
-
-        if *language == TargetLanguage::Russian {
-            let stripped = remove_diacritics(word_str);
-            if stripped != word_str && word_set.contains(&(stripped.clone(), language.clone())) {
-                return HyperlinkedText::Link(word_str.to_string());
-            }
-        }
-
-        HyperlinkedText::Plain(word_str.to_string())
     };
 
-    for c in text.chars() {
-        if is_non_content(&c) {
-            if was_last_filler {
-                current_word.push(c);
-            } else {
-                if !current_word.is_empty() {
-                    result.push(process_word(&current_word));
-                    current_word.clear();
-                }
-                current_word.push(c);
-            }
-            was_last_filler = true;
-        } else {
-            if was_last_filler {
-                result.push(HyperlinkedText::Plain(current_word.clone()));
-                current_word.clear();
+    tokenize(&text)
+        .into_iter()
+        .flat_map(|token| {
+            if !token.is_word {
+                return vec![HyperlinkedText::Plain(token.text.to_string())];
             }
-            current_word.push(c);
-            was_last_filler = false;
-        }
-    }
 
-    if !current_word.is_empty() {
-        if was_last_filler {
-            result.push(HyperlinkedText::Plain(current_word));
-        } else {
-            result.push(process_word(&current_word));
-        }
-    }
+            let needs_segmenting = token.text.chars().any(is_scriptio_continua)
+                && !word_set.contains(&(token.text.to_string(), language.clone()));
+            if !needs_segmenting {
+                return vec![process_word(token.text)];
+            }
 
-    result
+            // No frequency data at build time, so every known word costs the same and the
+            // segmenter falls back to plain maximum matching.
+            segment(token.text, |w| {
+                word_set
+                    .contains(&(w.to_string(), language.clone()))
+                    .then_some(1.0)
+            })
+            .into_iter()
+            .map(|s| process_word(&token.text[s.start..s.end]))
+            .collect()
+        })
+        .collect()
 }
 
 // Tests have been removed for brevity to avoid confusion. You can re-add them if needed.
@@ -467,7 +631,7 @@ mod tests {
         let mut word_set = HashSet::new();
         word_set.insert(("bonjour".to_string(), TargetLanguage::French));
         let input = "bonjour".to_string();
-        let expected = vec![HyperlinkedText::Link("bonjour".to_string())];
+        let expected = vec![HyperlinkedText::link("bonjour")];
         assert_eq!(
             hyperlink_text(input, &word_set, &TargetLanguage::French),
             expected
@@ -493,13 +657,97 @@ mod tests {
         assert_eq!(
             hyperlink_text(input, &word_set, &TargetLanguage::French),
             vec![
-                HyperlinkedText::Link("bonjour".to_string()),
+                HyperlinkedText::link("bonjour"),
                 HyperlinkedText::Plain(" ".to_string()),
                 HyperlinkedText::Plain("hallo".to_string()),
             ]
         );
     }
 
+    #[test]
+    fn test_get_forms_skips_table_tags() {
+        let json: Value = serde_json::from_str(
+            r#"{"forms": [
+                {"form": "de-ndecl", "tags": ["table-tags"]},
+                {"form": "Hauses", "tags": ["genitive", "singular"]},
+                {"form": "-", "tags": ["genitive", "plural"]}
+            ]}"#,
+        )
+        .unwrap();
+
+        let forms = get_forms(&json);
+        assert_eq!(forms.len(), 1);
+        assert_eq!(forms[0].form, "Hauses");
+        assert_eq!(forms[0].analysis.case.as_deref(), Some("genitive"));
+        assert_eq!(forms[0].analysis.number.as_deref(), Some("singular"));
+    }
+
+    #[test]
+    fn test_merge_pronunciations_keeps_labels() {
+        let pronunciation = |ipa: &str, tag: &str| Pronunciation {
+            ipa: Some(ipa.to_string()),
+            ogg_url: None,
+            mp3_url: None,
+            tags: vec![tag.to_string()],
+        };
+        let mut pronunciations = vec![
+            pronunciation("/haʊ̯s/", "Germany"),
+            pronunciation("/haʊ̯s/", "Austria"),
+            pronunciation("/hɑʊ̯s/", "Switzerland"),
+        ];
+
+        merge_pronunciations(&mut pronunciations);
+
+        assert_eq!(pronunciations.len(), 2);
+        assert_eq!(pronunciations[0].tags, vec!["Germany", "Austria"]);
+        assert_eq!(pronunciations[1].tags, vec!["Switzerland"]);
+    }
+
+    #[test]
+    fn test_hyperlink_segments_han_script() {
+        // Segmentation keys off the script rather than the language
+        let mut word_set = HashSet::new();
+        word_set.insert(("学生".to_string(), TargetLanguage::German));
+        word_set.insert(("我".to_string(), TargetLanguage::German));
+        let input = "我是学生".to_string();
+        assert_eq!(
+            hyperlink_text(input, &word_set, &TargetLanguage::German),
+            vec![
+                HyperlinkedText::link("我"),
+                HyperlinkedText::Plain("是".to_string()),
+                HyperlinkedText::link("学生"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_link_target_strips_stress() {
+        let mut word_set = HashSet::new();
+        word_set.insert(("указанный".to_string(), TargetLanguage::Russian));
+        let input = "ука́занный".to_string();
+        assert_eq!(
+            hyperlink_text(input, &word_set, &TargetLanguage::Russian),
+            vec![HyperlinkedText::Link {
+                text: "ука́занный".to_string(),
+                target: "указанный".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_link_target_case_variant() {
+        let mut word_set = HashSet::new();
+        word_set.insert(("maison".to_string(), TargetLanguage::French));
+        let input = "Maison".to_string();
+        assert_eq!(
+            hyperlink_text(input, &word_set, &TargetLanguage::French),
+            vec![HyperlinkedText::Link {
+                text: "Maison".to_string(),
+                target: "maison".to_string(),
+            }]
+        );
+    }
+
     #[test]
     fn test_no_change() {
         assert_eq!(solve_unopened_brackets("()".to_string()), "()".to_string());
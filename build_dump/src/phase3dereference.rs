@@ -1,4 +1,4 @@
-use libdictdefinition::{DictionaryElementData, HyperlinkedText};
+use libdictdefinition::{DictionaryElementData, HyperlinkedText, InflectedForm, LemmaReference};
 use std::collections::{HashMap, HashSet};
 use Languages::TargetLanguage;
 
@@ -43,33 +43,63 @@ pub fn process_dereferences(elements: Vec<DictionaryElementData>) -> Vec<Diction
     // This crucial line from your 6-month backup prevents multi-stage dereferencing (e.g. A -> B -> C).
     to_process.retain(|x| !to_process_keys.contains(&x.3));
 
-    // Perform dereferencing
+    // Perform dereferencing. The form keeps its own senses, audio and ipa and only stores a
+    // reference to the lemma, which the server resolves at lookup time. A copy of the lemma's
+    // form table is dropped too: `analysis_for` reads the table through the reference.
+    let mut reference_count = 0;
+    let mut bytes_saved: i64 = 0;
+    let mut tables_dropped = 0;
+
     for (key, lang, dereferenced_text, referenced_word) in to_process {
-        // Get the original element before we overwrite it. We need its audio/ipa.
-        let original_element = match element_map.get(&(key.clone(), lang.clone())) {
-            Some(el) => el.clone(),
-            None => continue, // Should be impossible, but safer to handle
+        let (Some(lemma), Some(form)) = (
+            element_map.get(&(referenced_word.clone(), lang.clone())),
+            element_map.get(&(key.clone(), lang.clone())),
+        ) else {
+            continue;
         };
 
-        if let Some(referenced_element) = element_map.get(&(referenced_word.clone(), lang.clone()))
-        {
-            let mut new_element = referenced_element.clone();
-            new_element.key = key.clone();
-            new_element.word = key.clone();
-            new_element.dereferenced_text = Some(dereferenced_text);
+        // What the dump used to store instead: a copy of the lemma under the form's key
+        let mut copy = DictionaryElementData {
+            key: key.clone(),
+            word: key.clone(),
+            dereferenced_text: Some(dereferenced_text.clone()),
+            ..lemma.clone()
+        };
+        if copy.audio.is_empty() {
+            copy.audio = form.audio.clone();
+        }
+        if copy.ipa.is_none() {
+            copy.ipa = form.ipa.clone();
+        }
+        let copy_size = bincode::serialized_size(&copy).unwrap_or(0) as i64;
 
-            // **THE FIX**: Preserve the original audio/ipa if the root element doesn't have it.
-            if new_element.audio.is_empty() {
-                new_element.audio = original_element.audio;
-            }
-            if new_element.ipa.is_none() {
-                new_element.ipa = original_element.ipa;
+        let lemma_forms: HashSet<&InflectedForm> = lemma.forms.iter().collect();
+        let shares_lemma_table =
+            !form.forms.is_empty() && form.forms.iter().all(|f| lemma_forms.contains(f));
+
+        if let Some(element) = element_map.get_mut(&(key, lang.clone())) {
+            if shares_lemma_table {
+                element.forms.clear();
+                tables_dropped += 1;
             }
+            element.dereferenced_text = Some(dereferenced_text);
+            element.lemma_reference = Some(LemmaReference {
+                lang,
+                key: referenced_word,
+            });
 
-            element_map.insert((key, lang), new_element);
+            reference_count += 1;
+            bytes_saved += copy_size - bincode::serialized_size(element).unwrap_or(0) as i64;
         }
     }
 
+    println!(
+        "Stored {} lemma references instead of copies and dropped {} copied form tables, saving {:.2} MB before compression",
+        reference_count,
+        tables_dropped,
+        bytes_saved as f64 / (1024.0 * 1024.0)
+    );
+
     element_map.into_values().collect()
 }
 
@@ -85,20 +115,20 @@ fn parse_dereference(text: &[HyperlinkedText]) -> Option<(String, String)> {
     'outer: for (i, item) in text.iter().enumerate() {
         let current_str = match item {
             HyperlinkedText::Plain(s) => s,
-            HyperlinkedText::Link(s) => s,
+            HyperlinkedText::Link { text, .. } => text,
         };
 
         if current_str.trim() == "of" {
             // Look ahead for a link. It's often separated by a space, so at `i + 2`.
             if let Some(next_item) = text.get(i + 1) {
-                if let HyperlinkedText::Link(word) = next_item {
+                if let HyperlinkedText::Link { target: word, .. } = next_item {
                     of_index = Some(i);
                     referenced_word = Some(word.clone());
                     break 'outer;
                 }
             }
             if let Some(next_item) = text.get(i + 2) {
-                if let HyperlinkedText::Link(word) = next_item {
+                if let HyperlinkedText::Link { target: word, .. } = next_item {
                     of_index = Some(i);
                     referenced_word = Some(word.clone());
                     break 'outer;
@@ -115,7 +145,7 @@ fn parse_dereference(text: &[HyperlinkedText]) -> Option<(String, String)> {
     let mut space_count_before = 0;
     for item in text.iter().take(of_index) {
         let s = match item {
-            HyperlinkedText::Plain(s) | HyperlinkedText::Link(s) => s,
+            HyperlinkedText::Plain(s) | HyperlinkedText::Link { text: s, .. } => s,
         };
         before_text_len += s.chars().count();
         space_count_before += count_whitespace(s);
@@ -129,7 +159,7 @@ fn parse_dereference(text: &[HyperlinkedText]) -> Option<(String, String)> {
     let link_pos = text
         .iter()
         .position(|item| match item {
-            HyperlinkedText::Link(w) => w == &referenced_word,
+            HyperlinkedText::Link { target, .. } => target == &referenced_word,
             _ => false,
         })
         .unwrap_or(of_index); // Fallback to 'of' index
@@ -137,7 +167,7 @@ fn parse_dereference(text: &[HyperlinkedText]) -> Option<(String, String)> {
     let mut chars_after = 0;
     for item in text.iter().skip(link_pos + 1) {
         match item {
-            HyperlinkedText::Plain(s) | HyperlinkedText::Link(s) => {
+            HyperlinkedText::Plain(s) | HyperlinkedText::Link { text: s, .. } => {
                 chars_after += s.chars().count()
             }
         }
@@ -153,7 +183,7 @@ fn parse_dereference(text: &[HyperlinkedText]) -> Option<(String, String)> {
         .iter()
         .take(of_index)
         .map(|item| match item {
-            HyperlinkedText::Plain(s) | HyperlinkedText::Link(s) => s.as_str(),
+            HyperlinkedText::Plain(s) | HyperlinkedText::Link { text: s, .. } => s.as_str(),
         })
         .collect::<String>();
 
@@ -163,7 +193,7 @@ fn parse_dereference(text: &[HyperlinkedText]) -> Option<(String, String)> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use libdictdefinition::{Definition, HyperlinkedText};
+    use libdictdefinition::{Definition, GrammaticalAnalysis, HyperlinkedText};
 
     #[test]
     fn test_parse_dereference_bemerkt() {
@@ -174,7 +204,7 @@ mod tests {
             HyperlinkedText::Plain(" ".to_string()),
             HyperlinkedText::Plain("of".to_string()),
             HyperlinkedText::Plain(" ".to_string()),
-            HyperlinkedText::Link("bemerken".to_string()),
+            HyperlinkedText::link("bemerken"),
         ];
         let expected = Some(("past participle of".to_string(), "bemerken".to_string()));
         assert_eq!(parse_dereference(&input), expected);
@@ -190,7 +220,7 @@ mod tests {
             HyperlinkedText::Plain(" ".to_string()),
             HyperlinkedText::Plain("of".to_string()),
             HyperlinkedText::Plain(" ".to_string()),
-            HyperlinkedText::Link("дальше".to_string()),
+            HyperlinkedText::link("дальше"),
             HyperlinkedText::Plain(" (".to_string()),
             HyperlinkedText::Plain("dálʹše".to_string()),
             HyperlinkedText::Plain("): ".to_string()),
@@ -201,6 +231,78 @@ mod tests {
         assert_eq!(parse_dereference(&input), expected);
     }
 
+    fn element(key: &str, definitions: Vec<Definition>) -> DictionaryElementData {
+        DictionaryElementData {
+            word_types: vec!["verb".to_string()],
+            definitions,
+            ..DictionaryElementData::new(TargetLanguage::German, key)
+        }
+    }
+
+    #[test]
+    fn test_process_dereferences_references_lemma() {
+        let table = vec![InflectedForm {
+            form: "bemerkt".to_string(),
+            analysis: GrammaticalAnalysis {
+                tense: Some("past".to_string()),
+                other: vec!["participle".to_string()],
+                ..GrammaticalAnalysis::default()
+            },
+        }];
+        let mut lemma = element(
+            "bemerken",
+            vec![Definition {
+                text: vec![HyperlinkedText::Plain("to notice".to_string())],
+                tags: Vec::new(),
+            }],
+        );
+        lemma.forms = table.clone();
+        let mut form = element(
+            "bemerkt",
+            vec![
+                Definition {
+                    text: vec![
+                        HyperlinkedText::Plain("past".to_string()),
+                        HyperlinkedText::Plain(" ".to_string()),
+                        HyperlinkedText::Plain("participle".to_string()),
+                        HyperlinkedText::Plain(" ".to_string()),
+                        HyperlinkedText::Plain("of".to_string()),
+                        HyperlinkedText::Plain(" ".to_string()),
+                        HyperlinkedText::link("bemerken"),
+                    ],
+                    tags: vec!["Form-of".to_string()],
+                },
+                Definition {
+                    text: vec![HyperlinkedText::Plain("noticeable".to_string())],
+                    tags: Vec::new(),
+                },
+            ],
+        );
+
+        form.forms = table.clone();
+
+        let output = process_dereferences(vec![lemma, form.clone()]);
+        let dereferenced = output.iter().find(|e| e.key == "bemerkt").unwrap();
+
+        assert_eq!(dereferenced.definitions, form.definitions);
+        assert_eq!(
+            dereferenced.dereferenced_text.as_deref(),
+            Some("past participle of")
+        );
+        assert_eq!(
+            dereferenced.lemma_reference,
+            Some(LemmaReference {
+                lang: TargetLanguage::German,
+                key: "bemerken".to_string(),
+            })
+        );
+        assert!(dereferenced.resolved_lemma.is_none());
+        // The copied table is read through the reference instead
+        assert!(dereferenced.forms.is_empty());
+        let lemma = output.iter().find(|e| e.key == "bemerken").unwrap();
+        assert_eq!(lemma.forms, table);
+    }
+
     #[test]
     fn test_parse_dereference_too_long_after() {
         // This test ensures the safety check still works.
@@ -209,7 +311,7 @@ mod tests {
             HyperlinkedText::Plain(" ".to_string()),
             HyperlinkedText::Plain("of".to_string()),
             HyperlinkedText::Plain(" ".to_string()),
-            HyperlinkedText::Link("word".to_string()),
+            HyperlinkedText::link("word"),
             HyperlinkedText::Plain(" and a very long sentence follows here that should definitely fail the thirty character safety check".to_string()),
         ];
         assert_eq!(parse_dereference(&input), None);
@@ -0,0 +1,237 @@
+use serde::{Deserialize, Serialize};
+
+use crate::request_trace::record_query;
+use crate::AppState;
+use axum::{extract::State, http::StatusCode, Json};
+
+use crate::metrics::NoLabel;
+use libdictdefinition::segment::{is_scriptio_continua, Segment};
+use libdictdefinition::tokenize::{tokenize, Token};
+use metrics::{counter, histogram};
+use std::time::Instant;
+use Languages::TargetLanguage;
+
+const MAX_TEXT_CHARS: usize = 20_000;
+/// Longest multi-word entry tried, in words ("kick the bucket" is 3).
+const MAX_SPAN_WORDS: usize = 5;
+
+#[derive(Deserialize, Debug)]
+pub struct AnnotateRequest {
+    language: TargetLanguage,
+    text: String,
+}
+
+/// A word or multi-word expression. Offsets are in characters (Unicode scalar values) into the
+/// request text, end-exclusive.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct AnnotatedSpan {
+    start: usize,
+    end: usize,
+    text: String,
+    key: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AnnotateResponse {
+    spans: Vec<AnnotatedSpan>,
+}
+
+pub async fn annotate(
+    State(state): State<AppState>,
+    Json(payload): Json<AnnotateRequest>,
+) -> Result<Json<AnnotateResponse>, (StatusCode, String)> {
+    let label = [(
+        "language",
+        payload.language.to_extension_technical_format_n(),
+    )];
+    counter!("annotate_language", &label).increment(1);
+    record_query(&state.config, &payload.language, None);
+
+    if payload.text.chars().count() > MAX_TEXT_CHARS {
+        return Err((
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("Text longer than {} characters", MAX_TEXT_CHARS),
+        ));
+    }
+
+    let t_start = Instant::now();
+    let store = state.store()?;
+    let spans = state
+        .lookup_pool
+        .run(move || {
+            annotate_text(
+                &payload.text,
+                |candidate| store.resolve_key(payload.language.clone(), candidate),
+                |run| store.segment(payload.language.clone(), run),
+            )
+        })
+        .await?;
+    histogram!("annotate_duration_seconds", &[] as NoLabel).record(t_start.elapsed().as_secs_f64());
+
+    Ok(Json(AnnotateResponse { spans }))
+}
+
+/// Whether a filler token may sit inside a multi-word entry: "a priori", "well-known", "o'clock",
+/// but not across sentence punctuation.
+fn joins_words(filler: &Token) -> bool {
+    filler
+        .text
+        .chars()
+        .all(|c| c == ' ' || c == '-' || c == '\'')
+}
+
+/// Greedy longest match: at each word, try the longest run of up to `MAX_SPAN_WORDS` words first
+/// and take the first one `resolve` finds. Words nothing matched are returned with `key: None`.
+/// Words in scripts without spaces are split by `segment` instead.
+fn annotate_text(
+    text: &str,
+    resolve: impl Fn(&str) -> Option<String>,
+    segment: impl Fn(&str) -> Vec<Segment>,
+) -> Vec<AnnotatedSpan> {
+    let tokens = tokenize(text);
+
+    let mut char_starts = Vec::with_capacity(tokens.len() + 1);
+    let mut chars_so_far = 0;
+    for token in &tokens {
+        char_starts.push(chars_so_far);
+        chars_so_far += token.text.chars().count();
+    }
+    char_starts.push(chars_so_far);
+
+    let mut spans = Vec::new();
+    let mut i = 0;
+
+    while i < tokens.len() {
+        if !tokens[i].is_word {
+            i += 1;
+            continue;
+        }
+
+        let token = &tokens[i];
+        if token.text.chars().any(is_scriptio_continua) {
+            let mut char_start = char_starts[i];
+            for piece in segment(token.text) {
+                let piece_text = &token.text[piece.start..piece.end];
+                let char_end = char_start + piece_text.chars().count();
+                spans.push(AnnotatedSpan {
+                    start: char_start,
+                    end: char_end,
+                    text: piece_text.to_string(),
+                    key: if piece.known {
+                        Some(piece_text.to_string())
+                    } else {
+                        None
+                    },
+                });
+                char_start = char_end;
+            }
+            i += 1;
+            continue;
+        }
+
+        // Token indices of the words a span starting here could end on, shortest first
+        let mut ends = vec![i];
+        let mut j = i;
+        while ends.len() < MAX_SPAN_WORDS
+            && j + 2 < tokens.len()
+            && joins_words(&tokens[j + 1])
+            && tokens[j + 2].is_word
+        {
+            j += 2;
+            ends.push(j);
+        }
+
+        let mut matched = None;
+        for &end in ends.iter().rev() {
+            let candidate = &text[tokens[i].start..tokens[end].end];
+            if let Some(key) = resolve(candidate) {
+                matched = Some((end, key));
+                break;
+            }
+        }
+
+        let (end, key) = match matched {
+            Some((end, key)) => (end, Some(key)),
+            None => (i, None),
+        };
+
+        spans.push(AnnotatedSpan {
+            start: char_starts[i],
+            end: char_starts[end + 1],
+            text: text[tokens[i].start..tokens[end].end].to_string(),
+            key,
+        });
+        i = end + 1;
+    }
+
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolver(keys: &[&str]) -> impl Fn(&str) -> Option<String> {
+        let keys: Vec<String> = keys.iter().map(|k| k.to_string()).collect();
+        move |candidate| keys.iter().find(|k| *k == candidate).cloned()
+    }
+
+    fn no_segmenter(_: &str) -> Vec<Segment> {
+        panic!("nothing here needs segmenting")
+    }
+
+    #[test]
+    fn test_longest_match_wins() {
+        let spans = annotate_text(
+            "They kick the bucket.",
+            resolver(&["kick", "the", "bucket", "kick the bucket"]),
+            no_segmenter,
+        );
+
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[0].key, None);
+        assert_eq!(spans[1].text, "kick the bucket");
+        assert_eq!(spans[1].key.as_deref(), Some("kick the bucket"));
+        assert_eq!((spans[1].start, spans[1].end), (5, 20));
+    }
+
+    #[test]
+    fn test_no_match_across_punctuation() {
+        let spans = annotate_text("a, priori", resolver(&["a priori", "priori"]), no_segmenter);
+
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[0].key, None);
+        assert_eq!(spans[1].key.as_deref(), Some("priori"));
+    }
+
+    #[test]
+    fn test_offsets_are_characters() {
+        let spans = annotate_text("Привет мир", resolver(&["мир"]), no_segmenter);
+
+        assert_eq!((spans[1].start, spans[1].end), (7, 10));
+        assert_eq!(spans[1].key.as_deref(), Some("мир"));
+    }
+
+    #[test]
+    fn test_segments_scriptio_continua() {
+        let words = ["我", "学生"];
+        let spans = annotate_text("我是学生。", resolver(&[]), |run| {
+            libdictdefinition::segment::segment(run, |w| words.contains(&w).then_some(1.0))
+        });
+
+        let texts: Vec<(&str, Option<&str>)> = spans
+            .iter()
+            .map(|s| (s.text.as_str(), s.key.as_deref()))
+            .collect();
+        assert_eq!(
+            texts,
+            vec![
+                ("我", Some("我")),
+                ("是", None),
+                ("学生", Some("学生")),
+                ("。", None)
+            ]
+        );
+        assert_eq!((spans[2].start, spans[2].end), (2, 4));
+    }
+}
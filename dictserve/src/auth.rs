@@ -0,0 +1,390 @@
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header::RETRY_AFTER, HeaderMap, HeaderName, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use ipnet::IpNet;
+use metrics::counter;
+use moka::sync::Cache;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::config::{Config, ConfigError};
+use crate::AppState;
+
+pub const API_KEY_HEADER: &str = "x-api-key";
+const FORWARDED_FOR_HEADER: HeaderName = HeaderName::from_static("x-forwarded-for");
+/// Label for requests without a key in per-client metrics.
+const ANONYMOUS: &str = "anonymous";
+/// Most clients tracked per limiter; the least recently seen are forgotten first.
+const MAX_TRACKED_CLIENTS: u64 = 100_000;
+/// Slowest allowed refill, one request a day. Slower limits would mean waits that don't fit in a
+/// `Duration`, and are better expressed by not issuing a key.
+pub const MIN_PER_SECOND: f64 = 1.0 / 86_400.0;
+/// Longest `Retry-After` sent, and longest an idle client's bucket is remembered.
+const MAX_WAIT: Duration = Duration::from_secs(86_400);
+
+/// `secs` as a `Duration`, capped at `MAX_WAIT`.
+fn wait(secs: f64) -> Duration {
+    Duration::try_from_secs_f64(secs).map_or(MAX_WAIT, |wait| wait.min(MAX_WAIT))
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimit {
+    /// Sustained requests per second.
+    pub per_second: f64,
+    /// Bucket size: requests allowed at once after a quiet period.
+    pub burst: u32,
+}
+
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn full(limit: &RateLimit, now: Instant) -> Self {
+        Self {
+            tokens: limit.burst as f64,
+            updated: now,
+        }
+    }
+
+    /// Takes a token, or says how long until one is available.
+    fn take(&mut self, limit: &RateLimit, now: Instant) -> Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.per_second).min(limit.burst as f64);
+        self.updated = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(wait((1.0 - self.tokens) / limit.per_second))
+        }
+    }
+}
+
+struct RateLimiter<K> {
+    limit: RateLimit,
+    buckets: Cache<K, Arc<Mutex<TokenBucket>>>,
+}
+
+impl<K: std::hash::Hash + Eq + Send + Sync + 'static> RateLimiter<K> {
+    fn new(limit: RateLimit) -> Self {
+        // An idle bucket refills completely in burst / rate seconds, after which forgetting it
+        // changes nothing
+        let refill = wait(limit.burst as f64 / limit.per_second);
+        Self {
+            limit,
+            buckets: Cache::builder()
+                .max_capacity(MAX_TRACKED_CLIENTS)
+                .time_to_idle(refill.max(Duration::from_secs(1)))
+                .build(),
+        }
+    }
+
+    fn check(&self, client: K) -> Result<(), Duration> {
+        let now = Instant::now();
+        let bucket = self.buckets.get_with(client, || {
+            Arc::new(Mutex::new(TokenBucket::full(&self.limit, now)))
+        });
+        let mut bucket = bucket.lock().unwrap();
+        bucket.take(&self.limit, now)
+    }
+}
+
+/// API keys and the rate limiters guarding every route.
+pub struct Auth {
+    /// Key → client name. Metrics are labelled with the name so keys never reach them.
+    keys: HashMap<String, String>,
+    require_key: bool,
+    per_key: Option<RateLimiter<String>>,
+    per_ip: Option<RateLimiter<IpAddr>>,
+    trusted_proxies: Vec<IpNet>,
+}
+
+impl Auth {
+    pub fn from_config(config: &Config) -> Result<Self, ConfigError> {
+        let keys = match &config.api_keys_file {
+            Some(file) => std::fs::read_to_string(file)
+                .map_err(|e| format!("cannot read {}: {}", file, e))
+                .and_then(|content| {
+                    parse_keys(&content).map_err(|e| format!("{} is not a keys table: {}", file, e))
+                })
+                .map_err(|message| ConfigError::invalid("api_keys_file", message))?,
+            None => HashMap::new(),
+        };
+
+        Ok(Self {
+            keys,
+            require_key: config.require_api_key,
+            per_key: config.key_rate_limit.map(RateLimiter::new),
+            per_ip: config.ip_rate_limit.map(RateLimiter::new),
+            trusted_proxies: config.trusted_proxies.clone(),
+        })
+    }
+
+    fn is_trusted_proxy(&self, ip: IpAddr) -> bool {
+        self.trusted_proxies.iter().any(|net| net.contains(&ip))
+    }
+
+    /// The address that sent the request. Behind trusted proxies that's the last
+    /// `X-Forwarded-For` hop they didn't add themselves; anyone else could write the header, so
+    /// it's ignored for other peers.
+    fn client_ip(&self, headers: &HeaderMap, peer: IpAddr) -> IpAddr {
+        if !self.is_trusted_proxy(peer) {
+            return peer;
+        }
+
+        let hops: Vec<&str> = headers
+            .get_all(FORWARDED_FOR_HEADER)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .collect();
+        let mut client = peer;
+        for hop in hops.into_iter().rev() {
+            // A garbled hop could be anything, so stop at the last proxy that's known
+            let Ok(ip) = hop.trim().parse() else {
+                break;
+            };
+            client = ip;
+            if !self.is_trusted_proxy(ip) {
+                break;
+            }
+        }
+        client
+    }
+
+    /// The client name for these headers, or why the request is refused.
+    fn admit(&self, headers: &HeaderMap, ip: IpAddr) -> Result<String, Rejection> {
+        let key = headers.get(API_KEY_HEADER).and_then(|v| v.to_str().ok());
+
+        let (client, limited) = match key {
+            Some(key) => {
+                let client = self.keys.get(key).ok_or(Rejection::UnknownKey)?;
+                let limited = self
+                    .per_key
+                    .as_ref()
+                    .and_then(|limiter| limiter.check(client.clone()).err());
+                (client.clone(), limited)
+            }
+            None if self.require_key => return Err(Rejection::KeyRequired),
+            // Keyed clients may share an address (servers, NAT), so only anonymous traffic is
+            // limited per IP
+            None => {
+                let limited = self
+                    .per_ip
+                    .as_ref()
+                    .and_then(|limiter| limiter.check(ip).err());
+                (ANONYMOUS.to_string(), limited)
+            }
+        };
+
+        if let Some(retry_after) = limited {
+            counter!("api_rate_limited", &[("client", client)]).increment(1);
+            return Err(Rejection::RateLimited(retry_after));
+        }
+
+        Ok(client)
+    }
+}
+
+/// Keys file: a TOML table of client name → key, e.g. `extension = "3f9c..."`.
+fn parse_keys(content: &str) -> Result<HashMap<String, String>, toml::de::Error> {
+    let by_name: HashMap<String, String> = toml::from_str(content)?;
+    Ok(by_name.into_iter().map(|(name, key)| (key, name)).collect())
+}
+
+#[derive(Debug, PartialEq)]
+enum Rejection {
+    UnknownKey,
+    KeyRequired,
+    /// With the time until the client's bucket has a token again.
+    RateLimited(Duration),
+}
+
+impl IntoResponse for Rejection {
+    fn into_response(self) -> Response {
+        match self {
+            Rejection::UnknownKey => {
+                (StatusCode::UNAUTHORIZED, "Unknown API key".to_string()).into_response()
+            }
+            Rejection::KeyRequired => {
+                (StatusCode::UNAUTHORIZED, "API key required".to_string()).into_response()
+            }
+            Rejection::RateLimited(retry_after) => {
+                let seconds = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+                (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    [(RETRY_AFTER, seconds.to_string())],
+                    "Rate limit exceeded".to_string(),
+                )
+                    .into_response()
+            }
+        }
+    }
+}
+
+pub async fn guard(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let ip = state.auth.client_ip(request.headers(), addr.ip());
+    match state.auth.admit(request.headers(), ip) {
+        Ok(client) => {
+            counter!("api_requests", &[("client", client)]).increment(1);
+            next.run(request).await
+        }
+        Err(rejection) => rejection.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMIT: RateLimit = RateLimit {
+        per_second: 2.0,
+        burst: 3,
+    };
+
+    fn auth(require_key: bool) -> Auth {
+        Auth {
+            keys: parse_keys("extension = \"secret\"").unwrap(),
+            require_key,
+            per_key: Some(RateLimiter::new(LIMIT)),
+            per_ip: Some(RateLimiter::new(RateLimit {
+                per_second: 1.0,
+                burst: 1,
+            })),
+            trusted_proxies: vec!["10.0.0.0/8".parse().unwrap()],
+        }
+    }
+
+    fn with_key(key: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(API_KEY_HEADER, key.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_token_bucket_refills() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::full(&LIMIT, start);
+
+        for _ in 0..3 {
+            assert!(bucket.take(&LIMIT, start).is_ok());
+        }
+        assert_eq!(bucket.take(&LIMIT, start), Err(Duration::from_millis(500)));
+        assert!(bucket
+            .take(&LIMIT, start + Duration::from_millis(500))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_keys_map_to_client_names() {
+        let auth = auth(true);
+        let ip = IpAddr::from([127, 0, 0, 1]);
+
+        assert_eq!(auth.admit(&with_key("secret"), ip).unwrap(), "extension");
+        assert_eq!(
+            auth.admit(&with_key("wrong"), ip),
+            Err(Rejection::UnknownKey)
+        );
+        assert_eq!(
+            auth.admit(&HeaderMap::new(), ip),
+            Err(Rejection::KeyRequired)
+        );
+    }
+
+    #[test]
+    fn test_limits_return_retry_after() {
+        let auth = auth(false);
+        let ip = IpAddr::from([127, 0, 0, 1]);
+
+        assert_eq!(auth.admit(&HeaderMap::new(), ip).unwrap(), ANONYMOUS);
+        let limited = auth
+            .admit(&HeaderMap::new(), ip)
+            .unwrap_err()
+            .into_response();
+        assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(limited.headers()[RETRY_AFTER], "1");
+
+        // The key has its own, larger bucket
+        for _ in 0..3 {
+            assert!(auth.admit(&with_key("secret"), ip).is_ok());
+        }
+        assert!(auth.admit(&with_key("secret"), ip).is_err());
+    }
+
+    #[test]
+    fn test_waits_are_capped() {
+        let limit = RateLimit {
+            per_second: f64::MIN_POSITIVE,
+            burst: 1,
+        };
+        let start = Instant::now();
+        let mut bucket = TokenBucket::full(&limit, start);
+
+        assert!(bucket.take(&limit, start).is_ok());
+        assert_eq!(bucket.take(&limit, start), Err(MAX_WAIT));
+        RateLimiter::<IpAddr>::new(limit);
+    }
+
+    #[test]
+    fn test_forwarded_for_only_from_trusted_proxies() {
+        let auth = auth(false);
+        let proxy = IpAddr::from([10, 0, 0, 1]);
+        let stranger = IpAddr::from([203, 0, 113, 9]);
+        let forwarded = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(FORWARDED_FOR_HEADER, value.parse().unwrap());
+            headers
+        };
+
+        // The client can prepend anything; only the hop the proxy appended counts
+        assert_eq!(
+            auth.client_ip(&forwarded("1.1.1.1, 198.51.100.7, 10.0.0.2"), proxy),
+            IpAddr::from([198, 51, 100, 7])
+        );
+        assert_eq!(
+            auth.client_ip(&forwarded("198.51.100.7"), stranger),
+            stranger
+        );
+        assert_eq!(auth.client_ip(&HeaderMap::new(), proxy), proxy);
+        assert_eq!(
+            auth.client_ip(&forwarded("garbage, 10.0.0.2"), proxy),
+            IpAddr::from([10, 0, 0, 2])
+        );
+    }
+
+    #[test]
+    fn test_bad_keys_file_names_the_field() {
+        let config = |file: &str| -> Config {
+            toml::from_str(&format!(
+                "listen_address = \"127.0.0.1\"\nlisten_port = 8080\ndump_path = \"d\"\napi_keys_file = {:?}",
+                file
+            ))
+            .unwrap()
+        };
+        let malformed = std::env::temp_dir().join("dictserve_test_bad_keys.toml");
+        std::fs::write(&malformed, "extension = 3").unwrap();
+
+        for file in ["/nonexistent/keys.toml", malformed.to_str().unwrap()] {
+            assert!(matches!(
+                Auth::from_config(&config(file)),
+                Err(ConfigError::Invalid { field, .. }) if field == "api_keys_file"
+            ));
+        }
+        std::fs::remove_file(malformed).unwrap();
+    }
+}
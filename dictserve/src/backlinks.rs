@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+
+use crate::request_trace::record_query;
+use crate::AppState;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+
+use libdictdefinition::BACKLINK_CAP;
+use metrics::counter;
+use Languages::TargetLanguage;
+
+const DEFAULT_LIMIT: usize = 20;
+
+#[derive(Deserialize, Debug)]
+pub struct BacklinksRequest {
+    language: TargetLanguage,
+    word: String,
+    limit: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BacklinksResponse {
+    key: String,
+    backlinks: Vec<String>,
+}
+
+pub async fn get_backlinks(
+    State(state): State<AppState>,
+    Query(payload): Query<BacklinksRequest>,
+) -> Result<Json<BacklinksResponse>, (StatusCode, String)> {
+    let label = [(
+        "language",
+        payload.language.to_extension_technical_format_n(),
+    )];
+    counter!("backlinks_query_language", &label).increment(1);
+    record_query(&state.config, &payload.language, Some(&payload.word));
+
+    let limit = payload.limit.unwrap_or(DEFAULT_LIMIT).min(BACKLINK_CAP);
+
+    match state
+        .store()?
+        .backlinks(payload.language.clone(), &payload.word, limit)
+    {
+        Some((key, backlinks)) => Ok(Json(BacklinksResponse { key, backlinks })),
+        None => Err((StatusCode::NOT_FOUND, "Word not found".to_string())),
+    }
+}
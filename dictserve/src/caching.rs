@@ -0,0 +1,109 @@
+use axum::http::header::{CACHE_CONTROL, ETAG, IF_NONE_MATCH, LAST_MODIFIED};
+use axum::http::{HeaderMap, HeaderName};
+use std::time::{Duration, UNIX_EPOCH};
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::config::Config;
+
+/// Validator for a response body. It hashes exactly the bytes sent, with a hash that doesn't
+/// change between builds or machines, so a rebuilt dump or another replica only invalidates
+/// caches for the entries that actually changed.
+pub fn etag(body: &[u8]) -> String {
+    format!("\"{:016x}\"", xxh3_64(body))
+}
+
+/// Whether `If-None-Match` lists `etag` (or is `*`). Weak validators compare equal to strong ones,
+/// as RFC 9110 requires for this header. Only call it for a representation that exists: `*`
+/// must not turn a 404 into a 304.
+pub fn is_not_modified(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+/// `Cache-Control` for definitions. While keys are checked or limited, a shared cache would hand
+/// one client's response to clients without a key, so only the client itself may cache it.
+fn cache_control(config: &Config) -> String {
+    if config.require_api_key || config.key_rate_limit.is_some() {
+        format!("private, max-age={}", config.http_max_age_secs)
+    } else {
+        format!(
+            "public, max-age={}, s-maxage={}",
+            config.http_max_age_secs, config.http_shared_max_age_secs
+        )
+    }
+}
+
+/// Headers sent with both 200 and 304 responses.
+pub fn cache_headers(config: &Config, etag: String, built_at: u64) -> [(HeaderName, String); 3] {
+    [
+        (ETAG, etag),
+        (CACHE_CONTROL, cache_control(config)),
+        (
+            LAST_MODIFIED,
+            httpdate::fmt_http_date(UNIX_EPOCH + Duration::from_secs(built_at)),
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn if_none_match(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(IF_NONE_MATCH, value.parse().unwrap());
+        headers
+    }
+
+    /// Replicas and rebuilds must agree on the tag, so it's pinned rather than just compared.
+    #[test]
+    fn test_etag_is_a_stable_content_hash() {
+        let tag = etag(br#"{"word":"Haus"}"#);
+        assert_eq!(tag, "\"42a1b8d6762ec8c4\"");
+        assert_ne!(tag, etag(br#"{"word":"haus"}"#));
+    }
+
+    #[test]
+    fn test_keyed_responses_stay_out_of_shared_caches() {
+        let config = |extra: &str| -> Config {
+            toml::from_str(&format!(
+                "listen_address = \"127.0.0.1\"\nlisten_port = 8080\ndump_path = \"d\"\n{}",
+                extra
+            ))
+            .unwrap()
+        };
+
+        assert_eq!(
+            cache_control(&config("")),
+            "public, max-age=3600, s-maxage=86400"
+        );
+        assert_eq!(
+            cache_control(&config("require_api_key = true")),
+            "private, max-age=3600"
+        );
+        assert_eq!(
+            cache_control(&config("key_rate_limit = { per_second = 1.0, burst = 1 }")),
+            "private, max-age=3600"
+        );
+    }
+
+    #[test]
+    fn test_if_none_match() {
+        let tag = etag(b"Haus");
+
+        assert!(is_not_modified(&if_none_match(&tag), &tag));
+        assert!(is_not_modified(&if_none_match(&format!("W/{}", tag)), &tag));
+        assert!(is_not_modified(
+            &if_none_match(&format!("\"other\", {}", tag)),
+            &tag
+        ));
+        assert!(is_not_modified(&if_none_match("*"), &tag));
+        assert!(!is_not_modified(&if_none_match("\"other\""), &tag));
+        assert!(!is_not_modified(&HeaderMap::new(), &tag));
+    }
+}
@@ -0,0 +1,121 @@
+use libdictdefinition::DictionaryElementData;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use Languages::TargetLanguage;
+
+use crate::dictionary::DictionaryStore;
+
+/// A word the dump must get right, e.g.
+/// `{ language = "German", word = "Haus", min_definitions = 2, has_ipa = true }`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Canary {
+    pub language: TargetLanguage,
+    pub word: String,
+    /// Fewest senses the entry may have.
+    #[serde(default)]
+    pub min_definitions: usize,
+    #[serde(default)]
+    pub has_ipa: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct CanaryFailure {
+    pub language: TargetLanguage,
+    pub word: String,
+    pub problem: String,
+}
+
+impl fmt::Display for CanaryFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}:{}: {}", self.language, self.word, self.problem)
+    }
+}
+
+/// Looks every canary up the way `/get_definition` would and reports the ones that fail.
+pub fn check(store: &DictionaryStore, canaries: &[Canary]) -> Vec<CanaryFailure> {
+    canaries
+        .iter()
+        .filter_map(|canary| {
+            let element = store
+                .query(canary.language.clone(), &canary.word)
+                .map(|query_match| query_match.element);
+            check_element(canary, element.as_deref()).map(|problem| CanaryFailure {
+                language: canary.language.clone(),
+                word: canary.word.clone(),
+                problem,
+            })
+        })
+        .collect()
+}
+
+fn check_element(canary: &Canary, element: Option<&DictionaryElementData>) -> Option<String> {
+    let element = match element {
+        Some(element) => element,
+        None => return Some("not found".to_string()),
+    };
+
+    if element.definitions.len() < canary.min_definitions {
+        return Some(format!(
+            "{} definitions, expected at least {}",
+            element.definitions.len(),
+            canary.min_definitions
+        ));
+    }
+
+    let has_ipa = element.ipa.is_some() || element.pronunciations.iter().any(|p| p.ipa.is_some());
+    if canary.has_ipa && !has_ipa {
+        return Some("no IPA".to_string());
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libdictdefinition::{Definition, HyperlinkedText};
+
+    fn canary(min_definitions: usize, has_ipa: bool) -> Canary {
+        Canary {
+            language: TargetLanguage::German,
+            word: "Haus".to_string(),
+            min_definitions,
+            has_ipa,
+        }
+    }
+
+    fn element(definitions: usize, ipa: Option<&str>) -> DictionaryElementData {
+        DictionaryElementData {
+            ipa: ipa.map(|s| s.to_string()),
+            definitions: (0..definitions)
+                .map(|i| Definition {
+                    text: vec![HyperlinkedText::Plain(format!("sense {}", i))],
+                    tags: Vec::new(),
+                })
+                .collect(),
+            ..DictionaryElementData::new(TargetLanguage::German, "Haus")
+        }
+    }
+
+    #[test]
+    fn test_check_element_expectations() {
+        assert_eq!(check_element(&canary(0, false), None).unwrap(), "not found");
+        assert_eq!(
+            check_element(&canary(2, false), Some(&element(1, None))).unwrap(),
+            "1 definitions, expected at least 2"
+        );
+        assert_eq!(
+            check_element(&canary(1, true), Some(&element(1, None))).unwrap(),
+            "no IPA"
+        );
+        assert!(check_element(&canary(2, true), Some(&element(3, Some("haʊ̯s")))).is_none());
+    }
+
+    #[test]
+    fn test_canary_defaults_only_require_existence() {
+        let canary: Canary = toml::from_str("language = \"German\"\nword = \"Haus\"").unwrap();
+
+        assert!(check_element(&canary, Some(&element(0, None))).is_none());
+    }
+}
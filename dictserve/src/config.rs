@@ -1,26 +1,716 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use tracing::level_filters::LevelFilter;
+use tracing_loki::url::Url;
+
+use crate::auth::{RateLimit, MIN_PER_SECOND};
+use crate::canary::Canary;
+use crate::misses::MissConfig;
+use ipnet::IpNet;
 use toml;
 
+pub const DEFAULT_CONFIG_PATH: &str = "./Config/config.toml";
+/// `DICTSERVE_LISTEN_PORT=8081` overrides `listen_port`; `__` separates nested keys, as in
+/// `DICTSERVE_LOKI__URL`.
+const ENV_PREFIX: &str = "DICTSERVE_";
+/// Top-level keys from before `[loki]` and `[metrics]` existed → the section and key replacing them.
+const LEGACY_KEYS: &[(&str, &str, &str)] = &[
+    ("loki_url", "loki", "url"),
+    ("loki_job", "loki", "job"),
+    ("metrics_bind", "metrics", "bind"),
+];
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
     pub listen_address: String,
     pub listen_port: u16,
-    pub loki_url: String,
-    pub loki_job: String,
-    pub metrics_bind: String,
     pub dump_path: String,
+    /// Where logs go; `loki` also needs the `[loki]` section.
+    #[serde(default = "default_log_sinks")]
+    pub log_sinks: Vec<LogSink>,
+    /// "error", "warn", "info", "debug", "trace" or "off".
+    #[serde(default = "default_log_level")]
+    pub log_level: String,
+    #[serde(default)]
+    pub loki: Option<LokiConfig>,
+    /// Adds the looked-up word to request spans, and so to every log line and trace. Off by
+    /// default: the words are what users read.
+    #[serde(default)]
+    pub log_query_words: bool,
+    /// Exports request spans to an OpenTelemetry collector. Needs the `otlp` cargo feature.
+    #[serde(default)]
+    pub otlp: Option<OtlpConfig>,
+    /// Without this section no metrics endpoint is served.
+    #[serde(default)]
+    pub metrics: Option<MetricsConfig>,
+    /// Decompressed entries kept in memory; 0 disables the cache.
+    #[serde(default = "default_cache_capacity")]
+    pub cache_capacity: u64,
+    /// Lookups allowed to run at once; defaults to the number of CPUs.
+    #[serde(default = "default_lookup_concurrency")]
+    pub lookup_concurrency: usize,
+    /// Time a lookup may spend queued and running before the request gets a 503.
+    #[serde(default = "default_lookup_timeout_ms")]
+    pub lookup_timeout_ms: u64,
+    /// `Cache-Control: max-age` for definitions, i.e. how long the extension may reuse one.
+    #[serde(default = "default_http_max_age_secs")]
+    pub http_max_age_secs: u64,
+    /// `Cache-Control: s-maxage`, the lifetime in shared caches such as the CDN. Unused while API
+    /// keys are required or rate limited, when responses are sent `private`.
+    #[serde(default = "default_http_shared_max_age_secs")]
+    pub http_shared_max_age_secs: u64,
+    /// TOML table of client name → API key. Without it every request is anonymous.
+    #[serde(default)]
+    pub api_keys_file: Option<String>,
+    /// Reject requests that don't send a known key in `X-Api-Key`.
+    #[serde(default)]
+    pub require_api_key: bool,
+    /// Limit for each API key; unset means unlimited.
+    #[serde(default)]
+    pub key_rate_limit: Option<RateLimit>,
+    /// Limit for each IP address sending requests without a key; unset means unlimited.
+    #[serde(default)]
+    pub ip_rate_limit: Option<RateLimit>,
+    /// Reverse proxies in front of dictserve, e.g. "10.0.0.0/8". Requests from them are limited
+    /// by the client address they add to `X-Forwarded-For` instead of their own.
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpNet>,
+    /// Origins browsers may call the API from, e.g. "https://app.example.com", or "*" for any.
+    /// Empty disables CORS.
+    #[serde(default)]
+    pub cors_allowed_origins: Vec<String>,
+    #[serde(default = "default_cors_allowed_methods")]
+    pub cors_allowed_methods: Vec<String>,
+    /// How long browsers may cache a preflight response.
+    #[serde(default = "default_cors_max_age_secs")]
+    pub cors_max_age_secs: u64,
+    /// Entries the dump must contain, checked after every load. `/readyz` reports not ready
+    /// and hot reloads are refused while any fails.
+    #[serde(default)]
+    pub canaries: Vec<Canary>,
+    /// After SIGTERM or SIGINT, how long in-flight requests get to finish.
+    #[serde(default = "default_shutdown_timeout_secs")]
+    pub shutdown_timeout_secs: u64,
+    /// Without this section missed words aren't recorded.
+    #[serde(default)]
+    pub misses: Option<MissConfig>,
+    /// Legacy keys the file used, to be logged once logging is up.
+    #[serde(skip)]
+    pub deprecations: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LogSink {
+    /// Human-readable console output.
+    Pretty,
+    /// One JSON object per line on stdout.
+    Json,
+    Loki,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct LokiConfig {
+    pub url: String,
+    #[serde(default = "default_loki_job")]
+    pub job: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct OtlpConfig {
+    /// gRPC endpoint of the collector.
+    #[serde(default = "default_otlp_endpoint")]
+    pub endpoint: String,
+    #[serde(default = "default_otlp_service_name")]
+    pub service_name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct MetricsConfig {
+    pub bind: String,
+    /// Histogram name → bucket upper bounds, replacing the defaults in `metrics.rs`.
+    #[serde(default)]
+    pub buckets: HashMap<String, Vec<f64>>,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Usage(String),
+    Read {
+        path: String,
+        source: std::io::Error,
+    },
+    Syntax(String),
+    /// `field` is the dotted path of the offending key, empty when the problem isn't one key's.
+    Invalid {
+        field: String,
+        message: String,
+    },
+}
+
+impl ConfigError {
+    pub fn invalid(field: &str, message: impl Into<String>) -> Self {
+        ConfigError::Invalid {
+            field: field.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Usage(message) => write!(f, "{}", message),
+            ConfigError::Read { path, source } => {
+                write!(f, "Cannot read config file {}: {}", path, source)
+            }
+            ConfigError::Syntax(message) => write!(f, "Config is not valid TOML: {}", message),
+            ConfigError::Invalid { field, message } if field.is_empty() => {
+                write!(f, "Invalid config: {}", message)
+            }
+            ConfigError::Invalid { field, message } => {
+                write!(f, "Invalid config field `{}`: {}", field, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// The config file named by `--config <path>` (or `--config=<path>`, `-c <path>`).
+pub fn path_from_args(mut args: impl Iterator<Item = String>) -> Result<String, ConfigError> {
+    let mut path = DEFAULT_CONFIG_PATH.to_string();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-c" | "--config" => {
+                path = args
+                    .next()
+                    .ok_or_else(|| ConfigError::Usage(format!("{} needs a path", arg)))?;
+            }
+            other => match other.strip_prefix("--config=") {
+                Some(value) => path = value.to_string(),
+                None => {
+                    return Err(ConfigError::Usage(format!(
+                        "Unknown argument {:?}; usage: dictserve [--config <path>]",
+                        other
+                    )))
+                }
+            },
+        }
+    }
+
+    Ok(path)
+}
+
+fn default_log_sinks() -> Vec<LogSink> {
+    vec![LogSink::Pretty]
+}
+
+fn default_log_level() -> String {
+    "info".to_string()
+}
+
+fn default_loki_job() -> String {
+    "dictserve".to_string()
+}
+
+fn default_otlp_endpoint() -> String {
+    "http://localhost:4317".to_string()
+}
+
+fn default_otlp_service_name() -> String {
+    "dictserve".to_string()
+}
+
+fn default_cache_capacity() -> u64 {
+    10_000
+}
+
+fn default_lookup_concurrency() -> usize {
+    std::thread::available_parallelism().map_or(4, |n| n.get())
+}
+
+fn default_lookup_timeout_ms() -> u64 {
+    2_000
+}
+
+fn default_http_max_age_secs() -> u64 {
+    3_600
+}
+
+fn default_http_shared_max_age_secs() -> u64 {
+    86_400
+}
+
+fn default_cors_allowed_methods() -> Vec<String> {
+    vec!["GET".to_string(), "POST".to_string()]
+}
+
+fn default_cors_max_age_secs() -> u64 {
+    3_600
+}
+
+fn default_shutdown_timeout_secs() -> u64 {
+    30
 }
 
 impl Config {
-    pub fn from_file(file: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        let content = std::fs::read_to_string(file)?;
-        let config: Config = toml::from_str(&content)?;
+    /// Reads `file`, applies `DICTSERVE_*` environment overrides and validates the result.
+    pub fn load(file: &str) -> Result<Self, ConfigError> {
+        let content = std::fs::read_to_string(file).map_err(|source| ConfigError::Read {
+            path: file.to_string(),
+            source,
+        })?;
+        Self::parse(&content, std::env::vars())
+    }
+
+    fn parse(
+        content: &str,
+        env: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self, ConfigError> {
+        let mut table: toml::Table = content
+            .parse()
+            .map_err(|e: toml::de::Error| ConfigError::Syntax(e.to_string()))?;
+        apply_env_overrides(&mut table, env)?;
+        let deprecations = migrate_legacy_keys(&mut table)?;
+
+        let mut config: Config = serde_path_to_error::deserialize(toml::Value::Table(table))
+            .map_err(|e| {
+                let field = e.path().to_string();
+                ConfigError::Invalid {
+                    field: if field == "." { String::new() } else { field },
+                    message: e.into_inner().to_string().trim_end().to_string(),
+                }
+            })?;
+        config.deprecations = deprecations;
+        config.validate()?;
         Ok(config)
     }
 
+    fn validate(&self) -> Result<(), ConfigError> {
+        self.log_level_filter()?;
+
+        if self.log_sinks.contains(&LogSink::Loki) && self.loki.is_none() {
+            return Err(ConfigError::invalid(
+                "log_sinks",
+                "the loki sink needs a [loki] section",
+            ));
+        }
+        if let Some(loki) = &self.loki {
+            Url::parse(&loki.url).map_err(|e| ConfigError::invalid("loki.url", e.to_string()))?;
+        }
+
+        if let Some(otlp) = &self.otlp {
+            if cfg!(not(feature = "otlp")) {
+                return Err(ConfigError::invalid(
+                    "otlp",
+                    "dictserve was built without the otlp feature",
+                ));
+            }
+            Url::parse(&otlp.endpoint)
+                .map_err(|e| ConfigError::invalid("otlp.endpoint", e.to_string()))?;
+        }
+
+        if self.lookup_concurrency == 0 {
+            return Err(ConfigError::invalid(
+                "lookup_concurrency",
+                "must be at least 1",
+            ));
+        }
+        if self.lookup_timeout_ms == 0 {
+            return Err(ConfigError::invalid(
+                "lookup_timeout_ms",
+                "must be at least 1",
+            ));
+        }
+
+        for (field, limit) in [
+            ("key_rate_limit", &self.key_rate_limit),
+            ("ip_rate_limit", &self.ip_rate_limit),
+        ] {
+            if let Some(limit) = limit {
+                if !(limit.per_second >= MIN_PER_SECOND && limit.per_second.is_finite()) {
+                    return Err(ConfigError::invalid(
+                        &format!("{}.per_second", field),
+                        format!("must be at least {:e} (one a day)", MIN_PER_SECOND),
+                    ));
+                }
+                if limit.burst == 0 {
+                    return Err(ConfigError::invalid(
+                        &format!("{}.burst", field),
+                        "must be at least 1",
+                    ));
+                }
+            }
+        }
+
+        if self.require_api_key && self.api_keys_file.is_none() {
+            return Err(ConfigError::invalid(
+                "api_keys_file",
+                "required by require_api_key, which would otherwise refuse every request",
+            ));
+        }
+
+        if let Some(metrics) = &self.metrics {
+            for (name, buckets) in &metrics.buckets {
+                // NaN compares false either way, so it would slip past the ordering check
+                if buckets.is_empty()
+                    || buckets.iter().any(|bound| !bound.is_finite())
+                    || buckets.windows(2).any(|pair| pair[0] >= pair[1])
+                {
+                    return Err(ConfigError::invalid(
+                        &format!("metrics.buckets.{}", name),
+                        "must be a non-empty, increasing list of finite numbers",
+                    ));
+                }
+            }
+        }
+
+        if let Some(misses) = &self.misses {
+            for (field, value) in [
+                ("misses.capacity", misses.capacity as u64),
+                ("misses.sample_every", misses.sample_every),
+                ("misses.export_interval_secs", misses.export_interval_secs),
+            ] {
+                if value == 0 {
+                    return Err(ConfigError::invalid(field, "must be at least 1"));
+                }
+            }
+
+            // The report lists what users looked up, so it's only open where nobody else can ask
+            let exposed = self
+                .metrics
+                .as_ref()
+                .is_some_and(|metrics| !is_loopback_bind(&metrics.bind));
+            if exposed && misses.admin_key.is_none() {
+                return Err(ConfigError::invalid(
+                    "misses.admin_key",
+                    "required while metrics.bind isn't a loopback address",
+                ));
+            }
+        }
+
+        // Building the layer checks the origins and methods
+        crate::cors::cors_layer(self).map(|_| ())
+    }
+
+    pub fn log_level_filter(&self) -> Result<LevelFilter, ConfigError> {
+        LevelFilter::from_str(&self.log_level)
+            .map_err(|e| ConfigError::invalid("log_level", e.to_string()))
+    }
+
     pub fn to_file(&self, file: &str) -> Result<(), Box<dyn std::error::Error>> {
         let content = toml::to_string(self)?;
         std::fs::write(file, content)?;
         Ok(())
     }
 }
+
+/// Whether a `host:port` bind address only accepts local connections.
+fn is_loopback_bind(bind: &str) -> bool {
+    match bind.parse::<SocketAddr>() {
+        Ok(addr) => addr.ip().is_loopback(),
+        Err(_) => bind
+            .rsplit_once(':')
+            .is_some_and(|(host, _)| host == "localhost"),
+    }
+}
+
+/// Moves `LEGACY_KEYS` into their sections and describes each move. Old configs always shipped
+/// logs to Loki, so one that sets `loki_url` without `log_sinks` keeps doing so.
+fn migrate_legacy_keys(table: &mut toml::Table) -> Result<Vec<String>, ConfigError> {
+    let mut deprecations = Vec::new();
+    let uses_legacy_loki = table.contains_key("loki_url") && !table.contains_key("log_sinks");
+
+    for &(old, section, key) in LEGACY_KEYS {
+        let Some(value) = table.remove(old) else {
+            continue;
+        };
+        let section_table = table
+            .entry(section)
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+            .as_table_mut()
+            .ok_or_else(|| ConfigError::invalid(section, "must be a section"))?;
+        if section_table.contains_key(key) {
+            return Err(ConfigError::invalid(
+                old,
+                format!("replaced by `{}.{}`, which is also set", section, key),
+            ));
+        }
+        section_table.insert(key.to_string(), value);
+        deprecations.push(format!(
+            "`{}` is deprecated; set `{}` in [{}] instead",
+            old, key, section
+        ));
+    }
+
+    if uses_legacy_loki {
+        table.insert(
+            "log_sinks".to_string(),
+            toml::Value::try_from(["pretty", "loki"]).unwrap(),
+        );
+    }
+    Ok(deprecations)
+}
+
+/// Values are read as TOML where they parse (`8081`, `true`, `["GET"]`) and as plain strings
+/// otherwise, so a string that looks like a number needs quotes: `DICTSERVE_LOKI__JOB='"42"'`.
+fn apply_env_overrides(
+    table: &mut toml::Table,
+    env: impl IntoIterator<Item = (String, String)>,
+) -> Result<(), ConfigError> {
+    for (name, raw) in env {
+        let Some(path) = name.strip_prefix(ENV_PREFIX) else {
+            continue;
+        };
+        let keys: Vec<String> = path.split("__").map(|k| k.to_lowercase()).collect();
+        let value = toml::from_str::<toml::Table>(&format!("v = {}", raw))
+            .ok()
+            .and_then(|mut t| t.remove("v"))
+            .unwrap_or_else(|| toml::Value::String(raw.clone()));
+
+        let (last, parents) = keys.split_last().expect("split yields at least one key");
+        let mut current = &mut *table;
+        for (depth, key) in parents.iter().enumerate() {
+            current = current
+                .entry(key.clone())
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+                .as_table_mut()
+                .ok_or_else(|| {
+                    ConfigError::invalid(
+                        &keys[..=depth].join("."),
+                        format!("{} sets a key inside it, but it isn't a section", name),
+                    )
+                })?;
+        }
+        current.insert(last.clone(), value);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINIMAL: &str = r#"
+        listen_address = "127.0.0.1"
+        listen_port = 8080
+        dump_path = "compressed_dict.bin"
+    "#;
+
+    fn env(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_optional_sections_default() {
+        let config = Config::parse(MINIMAL, env(&[])).unwrap();
+
+        assert_eq!(config.log_sinks, vec![LogSink::Pretty]);
+        assert!(config.loki.is_none());
+        assert!(config.metrics.is_none());
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        let config = Config::parse(
+            MINIMAL,
+            env(&[
+                ("DICTSERVE_LISTEN_PORT", "9090"),
+                ("DICTSERVE_LOG_SINKS", r#"["json", "loki"]"#),
+                ("DICTSERVE_LOKI__URL", "http://loki:3100"),
+                ("DICTSERVE_LOKI__JOB", r#""42""#),
+                ("OTHER_LISTEN_PORT", "1"),
+            ]),
+        )
+        .unwrap();
+
+        assert_eq!(config.listen_port, 9090);
+        assert_eq!(config.log_sinks, vec![LogSink::Json, LogSink::Loki]);
+        let loki = config.loki.unwrap();
+        assert_eq!(loki.url, "http://loki:3100");
+        assert_eq!(loki.job, "42");
+    }
+
+    fn invalid_field(content: &str, vars: &[(&str, &str)]) -> String {
+        match Config::parse(content, env(vars)) {
+            Err(ConfigError::Invalid { field, .. }) => field,
+            other => panic!("expected an invalid field, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_errors_name_the_field() {
+        assert_eq!(
+            invalid_field(MINIMAL, &[("DICTSERVE_LISTEN_PORT", "eighty")]),
+            "listen_port"
+        );
+        assert_eq!(
+            invalid_field(MINIMAL, &[("DICTSERVE_LOG_SINKS", r#"["loki"]"#)]),
+            "log_sinks"
+        );
+        assert_eq!(
+            invalid_field(MINIMAL, &[("DICTSERVE_LOKI__URL", "not a url")]),
+            "loki.url"
+        );
+        assert_eq!(
+            invalid_field(
+                MINIMAL,
+                &[("DICTSERVE_IP_RATE_LIMIT", "{ per_second = 0.0, burst = 5 }")]
+            ),
+            "ip_rate_limit.per_second"
+        );
+        assert_eq!(
+            invalid_field(
+                MINIMAL,
+                &[(
+                    "DICTSERVE_KEY_RATE_LIMIT",
+                    "{ per_second = 1e-300, burst = 5 }"
+                )]
+            ),
+            "key_rate_limit.per_second"
+        );
+        assert_eq!(
+            invalid_field(
+                MINIMAL,
+                &[("DICTSERVE_TRUSTED_PROXIES", r#"["10.0.0.0/33"]"#)]
+            ),
+            "trusted_proxies[0]"
+        );
+        assert_eq!(
+            invalid_field(MINIMAL, &[("DICTSERVE_LOG_LEVEL", "loud")]),
+            "log_level"
+        );
+        assert_eq!(
+            invalid_field(
+                MINIMAL,
+                &[(
+                    "DICTSERVE_METRICS",
+                    r#"{ bind = "127.0.0.1:9000", buckets = { response_size_bytes = [10.0, 5.0] } }"#
+                )]
+            ),
+            "metrics.buckets.response_size_bytes"
+        );
+        assert_eq!(
+            invalid_field(
+                MINIMAL,
+                &[(
+                    "DICTSERVE_METRICS",
+                    r#"{ bind = "127.0.0.1:9000", buckets = { response_size_bytes = [1.0, nan] } }"#
+                )]
+            ),
+            "metrics.buckets.response_size_bytes"
+        );
+        assert_eq!(
+            invalid_field(MINIMAL, &[("DICTSERVE_OTLP__ENDPOINT", "not a url")]),
+            if cfg!(feature = "otlp") {
+                "otlp.endpoint"
+            } else {
+                "otlp"
+            }
+        );
+
+        let missing = Config::parse("listen_port = 8080", env(&[])).unwrap_err();
+        assert!(missing.to_string().contains("listen_address"));
+    }
+
+    #[test]
+    fn test_exposed_miss_report_needs_a_key() {
+        let with_misses = |bind: &str| {
+            format!(
+                "{}\n[metrics]\nbind = \"{}\"\n[misses]\ncapacity = 10\n",
+                MINIMAL, bind
+            )
+        };
+
+        assert_eq!(
+            invalid_field(&with_misses("0.0.0.0:9000"), &[]),
+            "misses.admin_key"
+        );
+        assert!(Config::parse(
+            &with_misses("0.0.0.0:9000"),
+            env(&[("DICTSERVE_MISSES__ADMIN_KEY", "secret")])
+        )
+        .is_ok());
+        assert!(Config::parse(&with_misses("127.0.0.1:9000"), env(&[])).is_ok());
+        assert!(Config::parse(&with_misses("[::1]:9000"), env(&[])).is_ok());
+        assert!(Config::parse(&with_misses("localhost:9000"), env(&[])).is_ok());
+    }
+
+    #[test]
+    fn test_legacy_keys_are_migrated() {
+        let config = Config::parse(
+            &format!(
+                "{}\n{}",
+                MINIMAL,
+                r#"
+                loki_url = "http://loki:3100"
+                loki_job = "dict"
+                metrics_bind = "127.0.0.1:9000"
+                "#
+            ),
+            env(&[]),
+        )
+        .unwrap();
+
+        let loki = config.loki.unwrap();
+        assert_eq!(
+            (loki.url.as_str(), loki.job.as_str()),
+            ("http://loki:3100", "dict")
+        );
+        assert_eq!(config.metrics.unwrap().bind, "127.0.0.1:9000");
+        assert_eq!(config.log_sinks, vec![LogSink::Pretty, LogSink::Loki]);
+        assert_eq!(config.deprecations.len(), 3);
+
+        assert_eq!(
+            invalid_field(
+                &format!("metrics_bind = \"127.0.0.1:1\"\n{}", MINIMAL),
+                &[("DICTSERVE_METRICS__BIND", "127.0.0.1:2")]
+            ),
+            "metrics_bind"
+        );
+    }
+
+    #[test]
+    fn test_unknown_keys_are_rejected() {
+        assert_eq!(
+            invalid_field(MINIMAL, &[("DICTSERVE_CACHE_CAPACTY", "5")]),
+            "cache_capacty"
+        );
+        assert_eq!(
+            invalid_field(MINIMAL, &[("DICTSERVE_LOKI__ULR", "http://loki:3100")]),
+            "loki.ulr"
+        );
+    }
+
+    #[test]
+    fn test_path_from_args() {
+        let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            path_from_args(args(&[]).into_iter()).unwrap(),
+            DEFAULT_CONFIG_PATH
+        );
+        assert_eq!(
+            path_from_args(args(&["--config", "a.toml"]).into_iter()).unwrap(),
+            "a.toml"
+        );
+        assert_eq!(
+            path_from_args(args(&["--config=b.toml"]).into_iter()).unwrap(),
+            "b.toml"
+        );
+        assert!(path_from_args(args(&["--config"]).into_iter()).is_err());
+        assert!(path_from_args(args(&["--verbose"]).into_iter()).is_err());
+    }
+}
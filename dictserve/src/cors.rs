@@ -0,0 +1,141 @@
+use axum::http::header::{CONTENT_TYPE, ETAG, IF_NONE_MATCH, LAST_MODIFIED, RETRY_AFTER};
+use axum::http::{HeaderName, HeaderValue, Method};
+use std::time::Duration;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use crate::config::{Config, ConfigError};
+use crate::request_trace::REQUEST_ID_HEADER;
+
+/// CORS for browser clients. Wrapped around everything else, so preflight requests are answered
+/// here and never reach the API key check: browsers don't send custom headers on preflights.
+pub fn cors_layer(config: &Config) -> Result<CorsLayer, ConfigError> {
+    let origins = if config.cors_allowed_origins.iter().any(|o| o == "*") {
+        AllowOrigin::any()
+    } else {
+        let origins = config
+            .cors_allowed_origins
+            .iter()
+            .map(|origin| {
+                HeaderValue::from_str(origin).map_err(|_| {
+                    ConfigError::invalid(
+                        "cors_allowed_origins",
+                        format!("invalid origin {:?}", origin),
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        AllowOrigin::list(origins)
+    };
+
+    let methods = config
+        .cors_allowed_methods
+        .iter()
+        .map(|method| {
+            Method::from_bytes(method.to_uppercase().as_bytes()).map_err(|_| {
+                ConfigError::invalid(
+                    "cors_allowed_methods",
+                    format!("invalid method {:?}", method),
+                )
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(CorsLayer::new()
+        .allow_origin(origins)
+        .allow_methods(methods)
+        .allow_headers([
+            CONTENT_TYPE,
+            IF_NONE_MATCH,
+            HeaderName::from_static("x-api-key"),
+            REQUEST_ID_HEADER,
+        ])
+        .expose_headers([ETAG, LAST_MODIFIED, RETRY_AFTER, REQUEST_ID_HEADER])
+        .max_age(Duration::from_secs(config.cors_max_age_secs)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::header::{
+        ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_MAX_AGE,
+        ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD, ORIGIN,
+    };
+    use axum::http::{Request, StatusCode};
+    use axum::routing::get;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    fn config(origins: &str) -> Config {
+        toml::from_str(&format!(
+            r#"
+            listen_address = "127.0.0.1"
+            listen_port = 8080
+            dump_path = "dump.bin"
+            cors_allowed_origins = {}
+            "#,
+            origins
+        ))
+        .unwrap()
+    }
+
+    fn app(config: &Config) -> Router {
+        Router::new()
+            .route("/get_definition", get(|| async { "ok" }))
+            .layer(cors_layer(config).unwrap())
+    }
+
+    fn preflight(origin: &str) -> Request<Body> {
+        Request::builder()
+            .method(Method::OPTIONS)
+            .uri("/get_definition")
+            .header(ORIGIN, origin)
+            .header(ACCESS_CONTROL_REQUEST_METHOD, "GET")
+            .header(ACCESS_CONTROL_REQUEST_HEADERS, "x-api-key")
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_preflight_for_allowed_origin() {
+        let config = config(r#"["https://app.example.com"]"#);
+        let response = app(&config)
+            .oneshot(preflight("https://app.example.com"))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let headers = response.headers();
+        assert_eq!(
+            headers[ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://app.example.com"
+        );
+        let allowed = headers[ACCESS_CONTROL_ALLOW_HEADERS].to_str().unwrap();
+        assert!(allowed.contains("x-api-key") && allowed.contains("x-request-id"));
+        assert_eq!(headers[ACCESS_CONTROL_MAX_AGE], "3600");
+    }
+
+    #[tokio::test]
+    async fn test_other_origins_get_no_cors_headers() {
+        let config = config(r#"["https://app.example.com"]"#);
+        let response = app(&config)
+            .oneshot(preflight("https://evil.example.com"))
+            .await
+            .unwrap();
+
+        assert!(response
+            .headers()
+            .get(ACCESS_CONTROL_ALLOW_ORIGIN)
+            .is_none());
+    }
+
+    #[test]
+    fn test_invalid_method_names_the_field() {
+        let mut config = config("[]");
+        config.cors_allowed_methods = vec!["GE T".to_string()];
+        assert!(matches!(
+            cors_layer(&config),
+            Err(ConfigError::Invalid { field, .. }) if field == "cors_allowed_methods"
+        ));
+    }
+}
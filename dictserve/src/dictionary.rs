@@ -1,30 +1,110 @@
-use dashmap::DashMap;
+use crate::metrics::NoLabel;
+use libdictdefinition::index::PerfectHashIndex;
+use libdictdefinition::normalize::{
+    fold_diacritics, fold_romanization, lowercase_with_first_uppercase,
+};
+use libdictdefinition::segment::{segment, Segment};
 use libdictdefinition::{
-    CompressedDictionaryElementWrapper, Definition, DictionaryElementData, HyperlinkedText,
+    CompressedDictionaryElementWrapper, Definition, DictionaryDump, DictionaryElementData,
+    HyperlinkedText,
 };
-use rayon::prelude::*;
+use metrics::{counter, histogram};
+use moka::sync::Cache;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::Read;
+use std::sync::Arc;
 use std::time::Instant;
-use tracing::info;
+use tracing::{debug, info};
 use zstd::stream::decode_all;
 use Languages::TargetLanguage;
 include!(concat!(env!("OUT_DIR"), "/czech_lemmas.rs"));
 
 pub struct DictionaryStore {
-    datastore: DashMap<(TargetLanguage, String), CompressedDictionaryElementWrapper>,
+    datastore: PerfectHashIndex,
+    romanizations: HashMap<(TargetLanguage, String), Vec<String>>,
+    folded: HashMap<(TargetLanguage, String), Vec<String>>,
+    backlinks: HashMap<(TargetLanguage, String), Vec<String>>,
+    /// Decompressed entries with their lemma already resolved. Owned by the store, so loading a
+    /// new dump starts with an empty cache.
+    cache: Option<Cache<(TargetLanguage, String), Arc<DictionaryElementData>>>,
+    stats: StoreStats,
+}
+
+/// Facts about the loaded dump, computed once at load.
+#[derive(Clone, Debug, Serialize)]
+pub struct StoreStats {
+    pub entries: usize,
+    pub entries_by_language: HashMap<TargetLanguage, usize>,
+    pub compressed_bytes: u64,
+    pub build_id: String,
+    /// Seconds since the Unix epoch.
+    pub built_at: u64,
+    pub load_seconds: f32,
+}
+
+type Entry<'a> = &'a CompressedDictionaryElementWrapper;
+
+/// Which lookup step produced a hit, in the order they are tried.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchStep {
+    Exact,
+    Lowercase,
+    Titlecase,
+    /// Some other capitalisation or Unicode normalisation, e.g. "mcdonald" → "McDonald".
+    Variant,
+    Lemma,
+    Romanization,
+    Folded,
 }
 
-fn lowercase_with_first_uppercase(word: &str) -> String {
-    let mut chars = word.chars();
-    match chars.next() {
-        None => String::new(),
-        Some(first) => {
-            first.to_uppercase().collect::<String>() + chars.as_str().to_lowercase().as_str()
+impl MatchStep {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MatchStep::Exact => "exact",
+            MatchStep::Lowercase => "lowercase",
+            MatchStep::Titlecase => "titlecase",
+            MatchStep::Variant => "variant",
+            MatchStep::Lemma => "lemma",
+            MatchStep::Romanization => "romanization",
+            MatchStep::Folded => "folded",
         }
     }
 }
 
+pub struct QueryMatch {
+    /// Shared with the cache, so a hit costs no copy.
+    pub element: Arc<DictionaryElementData>,
+    pub step: MatchStep,
+    /// Set when only the accent-insensitive fallback matched: the folded form of the query.
+    pub folded_form: Option<String>,
+}
+
+/// Prefers the variant the old lookup cascade would have found first: exact, then all
+/// lowercase, then capitalised, then whatever else shares the normalised key.
+fn pick_case_variant<'a>(
+    key: &str,
+    candidates: &'a [CompressedDictionaryElementWrapper],
+) -> Option<(Entry<'a>, MatchStep)> {
+    if let Some(candidate) = candidates.iter().find(|c| c.key == key) {
+        return Some((candidate, MatchStep::Exact));
+    }
+
+    let all_lowercase = key.to_lowercase();
+    if let Some(candidate) = candidates.iter().find(|c| c.key == all_lowercase) {
+        return Some((candidate, MatchStep::Lowercase));
+    }
+
+    let with_first = lowercase_with_first_uppercase(key);
+    if let Some(candidate) = candidates.iter().find(|c| c.key == with_first) {
+        return Some((candidate, MatchStep::Titlecase));
+    }
+
+    candidates.first().map(|c| (c, MatchStep::Variant))
+}
+
 fn lemmatize_czech(word: &str) -> String {
     CZECH_LEMMAS
         .get(word)
@@ -33,103 +113,225 @@ fn lemmatize_czech(word: &str) -> String {
 }
 
 impl DictionaryStore {
-    pub fn from_elements_dump(path: &String) -> std::io::Result<Self> {
+    pub fn from_elements_dump(path: &String, cache_capacity: u64) -> std::io::Result<Self> {
         let start_t = Instant::now();
 
         let mut file = File::open(path)?;
         let mut buffer = Vec::new();
         file.read_to_end(&mut buffer)?;
-        let elements: Vec<CompressedDictionaryElementWrapper> =
-            bincode::deserialize(&buffer).unwrap();
-        let e_c = elements.len();
-        let store: DashMap<(TargetLanguage, String), CompressedDictionaryElementWrapper> =
-            DashMap::new();
-
-        // Use rayon to parallelize insertion
-        elements.into_par_iter().for_each(|element| {
-            store.insert((element.lang.clone(), element.key.clone()), element);
-        });
-
-        let time_taken = start_t.elapsed();
+        let dump: DictionaryDump = bincode::deserialize(&buffer)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+        let mut store = Self::from_dump(dump, cache_capacity);
+        store.stats.load_seconds = start_t.elapsed().as_secs_f32();
         info!(
             "Loaded {} items from elements dump in {}s",
-            e_c,
-            time_taken.as_secs_f32()
+            store.stats.entries, store.stats.load_seconds
         );
 
-        Ok(Self { datastore: store })
+        Ok(store)
     }
 
-    pub fn query(&self, lang: TargetLanguage, key: &str) -> Option<DictionaryElementData> {
-        let search_key = (lang.clone(), key.to_string());
-        //info!("Search key: {:?}", search_key);
+    pub fn from_dump(dump: DictionaryDump, cache_capacity: u64) -> Self {
+        let mut entries_by_language = HashMap::new();
+        let mut compressed_bytes = 0;
+        for element in dump.elements.iter() {
+            *entries_by_language.entry(element.lang.clone()).or_insert(0) += 1;
+            compressed_bytes += element.compressed_data.len() as u64;
+        }
 
-        // Try querying with the original key
-        let search_key = (lang.clone(), key.to_string());
-        if let Some(compressed_wrapper) = self.datastore.get(&search_key) {
-            return Some(self.decompress_element(compressed_wrapper.value()));
+        Self {
+            stats: StoreStats {
+                entries: dump.elements.len(),
+                entries_by_language,
+                compressed_bytes,
+                build_id: dump.build_id,
+                built_at: dump.built_at,
+                load_seconds: 0.0,
+            },
+            datastore: dump.elements,
+            romanizations: dump.romanizations,
+            folded: dump.folded,
+            backlinks: dump.backlinks,
+            cache: (cache_capacity > 0).then(|| Cache::new(cache_capacity)),
         }
+    }
 
-        // If not found, try again with the all-lowercase key
-        let all_lowercase = key.to_lowercase();
-        if key != all_lowercase {
-            let lower_key = (lang.clone(), all_lowercase.clone());
+    pub fn stats(&self) -> &StoreStats {
+        &self.stats
+    }
 
-            //info!("Search key: {:?}", lower_key);
+    pub fn query(&self, lang: TargetLanguage, key: &str) -> Option<QueryMatch> {
+        let t_start = Instant::now();
+        let found = self.find_any_entry(lang, key);
+        histogram!("dictionary_index_lookup_duration_seconds", &[] as NoLabel)
+            .record(t_start.elapsed().as_secs_f64());
 
-            if let Some(compressed_wrapper) = self.datastore.get(&lower_key) {
-                return Some(self.decompress_element(compressed_wrapper.value()));
-            }
-        }
+        // Without the word: the request span carries it only when `log_query_words` is on
+        let Some((entry, step, folded_form)) = found else {
+            debug!("No entry found");
+            return None;
+        };
+        debug!("Matched by {}", step.as_str());
+        let element = self.load_element(entry);
 
-        // Now try all lowercase with the first character uppercase
-        let with_first = lowercase_with_first_uppercase(key);
-        if with_first != key {
-            let with_key = (lang.clone(), with_first);
+        Some(QueryMatch {
+            element,
+            step,
+            folded_form,
+        })
+    }
 
-            //info!("Search key: {:?}", with_key);
+    /// The key `query` would return for this word, without decompressing anything.
+    pub fn resolve_key(&self, lang: TargetLanguage, key: &str) -> Option<String> {
+        self.find_any_entry(lang, key)
+            .map(|(entry, _, _)| entry.key.clone())
+    }
 
-            if let Some(compressed_wrapper) = self.datastore.get(&with_key) {
-                return Some(self.decompress_element(compressed_wrapper.value()));
-            }
+    /// Keys of entries whose definitions link to `key`, best first. `None` if the word itself
+    /// isn't in the dictionary.
+    pub fn backlinks(
+        &self,
+        lang: TargetLanguage,
+        key: &str,
+        limit: usize,
+    ) -> Option<(String, Vec<String>)> {
+        let resolved = self.resolve_key(lang.clone(), key)?;
+        let backlinks = self
+            .backlinks
+            .get(&(lang, resolved.clone()))
+            .map(|keys| keys.iter().take(limit).cloned().collect())
+            .unwrap_or_default();
+
+        Some((resolved, backlinks))
+    }
+
+    /// Splits text written without spaces (Chinese, Japanese, Thai, ...) into dictionary words.
+    /// There is no corpus frequency data, so the unigram cost uses compressed entry size as a
+    /// proxy: common words tend to have more senses and so larger entries.
+    pub fn segment(&self, lang: TargetLanguage, text: &str) -> Vec<Segment> {
+        segment(text, |word| {
+            self.datastore
+                .get(&lang, word)
+                .map(|entry| -(entry.compressed_data.len() as f32).ln())
+        })
+    }
+
+    fn find_any_entry(
+        &self,
+        lang: TargetLanguage,
+        key: &str,
+    ) -> Option<(Entry<'_>, MatchStep, Option<String>)> {
+        if let Some((entry, step)) = self.find_case_variant(&lang, key) {
+            return Some((entry, step, None));
         }
 
         if lang == TargetLanguage::Czech {
-            let mut stripped_key_slice = lemmatize_czech(key);
-            if stripped_key_slice == key {
-                stripped_key_slice = lemmatize_czech(key.to_lowercase().as_str());
+            let mut lemma = lemmatize_czech(key);
+            if lemma == key {
+                lemma = lemmatize_czech(key.to_lowercase().as_str());
             }
-            //info!("Lemma'd: {}", stripped_key_slice);
-
-            if stripped_key_slice != key {
-                let stripped_upper = stripped_key_slice.to_uppercase();
-                let stripped_upper_key = (lang.clone(), stripped_upper);
-                //info!("Key: {:?}", stripped_upper_key);
 
-                if let Some(compressed_wrapper) = self.datastore.get(&stripped_upper_key) {
-                    return Some(self.decompress_element(compressed_wrapper.value()));
+            if lemma != key {
+                if let Some((entry, _)) = self.find_case_variant(&lang, &lemma) {
+                    return Some((entry, MatchStep::Lemma, None));
                 }
+            }
+        }
 
-                let stripped_lower = stripped_key_slice.to_lowercase();
-                let stripped_lower_key = (lang.clone(), stripped_lower);
-                //info!("Key: {:?}", stripped_lower_key);
-                if let Some(compressed_wrapper) = self.datastore.get(&stripped_lower_key) {
-                    return Some(self.decompress_element(compressed_wrapper.value()));
+        // The user typed a native-script word in Latin letters
+        if let Some(candidates) = self
+            .romanizations
+            .get(&(lang.clone(), fold_romanization(key)))
+        {
+            for candidate in candidates {
+                if let Some(compressed_wrapper) = self.datastore.get(&lang, candidate) {
+                    return Some((compressed_wrapper, MatchStep::Romanization, None));
                 }
             }
         }
 
-        //info!("Got nothing for lang: {:?} key: {:?}", lang, key);
+        let (entry, folded_form) = self.find_folded_entry(lang, key)?;
+        Some((entry, MatchStep::Folded, Some(folded_form)))
+    }
+
+    /// A single index lookup covers every capitalisation and Unicode normalisation of the key.
+    fn find_case_variant(
+        &self,
+        lang: &TargetLanguage,
+        key: &str,
+    ) -> Option<(Entry<'_>, MatchStep)> {
+        pick_case_variant(key, self.datastore.variants(lang, key))
+    }
+
+    /// Accent- and stress-insensitive fallback: the folded query itself may be a key in some
+    /// capitalisation ("Приве́т" → "Привет"), or the folding index maps it back to accented keys
+    /// ("deja" → "déjà").
+    fn find_folded_entry(&self, lang: TargetLanguage, key: &str) -> Option<(Entry<'_>, String)> {
+        let folded = fold_diacritics(&lang, key);
+
+        if let Some((entry, _)) = self.find_case_variant(&lang, &folded) {
+            return Some((entry, folded));
+        }
+
+        let candidates = self.folded.get(&(lang.clone(), folded.clone()))?;
+        for candidate in candidates {
+            if let Some(compressed_wrapper) = self.datastore.get(&lang, candidate) {
+                return Some((compressed_wrapper, folded));
+            }
+        }
 
         None
     }
 
+    fn load_element(
+        &self,
+        compressed: &CompressedDictionaryElementWrapper,
+    ) -> Arc<DictionaryElementData> {
+        let cache = match &self.cache {
+            Some(cache) => cache,
+            None => {
+                let mut element = self.decompress_element(compressed);
+                self.resolve_lemma(&mut element);
+                return Arc::new(element);
+            }
+        };
+
+        let cache_key = (compressed.lang.clone(), compressed.key.clone());
+        if let Some(cached) = cache.get(&cache_key) {
+            counter!("dictionary_cache", &[("result", "hit")]).increment(1);
+            return cached;
+        }
+        counter!("dictionary_cache", &[("result", "miss")]).increment(1);
+
+        let mut element = self.decompress_element(compressed);
+        self.resolve_lemma(&mut element);
+        let element = Arc::new(element);
+        cache.insert(cache_key, element.clone());
+        element
+    }
+
+    fn resolve_lemma(&self, element: &mut DictionaryElementData) {
+        if let Some(reference) = &element.lemma_reference {
+            if let Some(compressed_wrapper) = self.datastore.get(&reference.lang, &reference.key) {
+                element.resolved_lemma =
+                    Some(Box::new(self.decompress_element(compressed_wrapper)));
+            }
+        }
+    }
+
     fn decompress_element(
         &self,
         compressed: &CompressedDictionaryElementWrapper,
     ) -> DictionaryElementData {
-        let decompressed_data: DictionaryElementData =
-            bincode::deserialize(&decode_all(&compressed.compressed_data[..]).unwrap()).unwrap();
+        let t_start = Instant::now();
+        let decompressed = decode_all(&compressed.compressed_data[..]).unwrap();
+        let decompressed_data: DictionaryElementData = bincode::deserialize(&decompressed).unwrap();
+
+        histogram!("dictionary_decompress_duration_seconds", &[] as NoLabel)
+            .record(t_start.elapsed().as_secs_f64());
+        histogram!("dictionary_decompressed_bytes", &[] as NoLabel)
+            .record(decompressed.len() as f64);
 
         decompressed_data
     }
@@ -139,6 +341,47 @@ impl DictionaryStore {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_stats_count_entries() {
+        let dump = DictionaryDump {
+            elements: PerfectHashIndex::build(vec![CompressedDictionaryElementWrapper {
+                key: "Haus".to_string(),
+                lang: TargetLanguage::German,
+                compressed_data: vec![0; 10],
+            }]),
+            ..Default::default()
+        };
+        let stats = DictionaryStore::from_dump(dump, 0).stats().clone();
+
+        assert_eq!(stats.entries, 1);
+        assert_eq!(stats.entries_by_language[&TargetLanguage::German], 1);
+        assert_eq!(stats.compressed_bytes, 10);
+
+        let json = serde_json::to_value(&stats).unwrap();
+        assert_eq!(json["entries_by_language"]["German"], 1);
+    }
+
+    #[test]
+    fn test_cache_hits_share_element() {
+        let element = DictionaryElementData::new(TargetLanguage::German, "Haus");
+        let compressed_data =
+            zstd::stream::encode_all(&bincode::serialize(&element).unwrap()[..], 0).unwrap();
+        let dump = DictionaryDump {
+            elements: PerfectHashIndex::build(vec![CompressedDictionaryElementWrapper {
+                key: "Haus".to_string(),
+                lang: TargetLanguage::German,
+                compressed_data,
+            }]),
+            ..Default::default()
+        };
+        let store = DictionaryStore::from_dump(dump, 10);
+
+        let first = store.query(TargetLanguage::German, "Haus").unwrap();
+        let second = store.query(TargetLanguage::German, "Haus").unwrap();
+        assert_eq!(*first.element, element);
+        assert!(Arc::ptr_eq(&first.element, &second.element));
+    }
+
     #[test]
     fn test_lemmatize_known_words() {
         assert_eq!(lemmatize_czech("Aachenu"), "Aachen");
@@ -150,4 +393,39 @@ mod tests {
     fn test_lemmatize_preserves_case() {
         assert_eq!(lemmatize_czech("Abrahámu"), "Abrahám");
     }
+
+    fn pick(key: &str, candidates: &[&str]) -> Option<(String, MatchStep)> {
+        let candidates: Vec<CompressedDictionaryElementWrapper> = candidates
+            .iter()
+            .map(|key| CompressedDictionaryElementWrapper {
+                key: key.to_string(),
+                lang: TargetLanguage::German,
+                compressed_data: Vec::new(),
+            })
+            .collect();
+        pick_case_variant(key, &candidates).map(|(entry, step)| (entry.key.clone(), step))
+    }
+
+    #[test]
+    fn test_pick_case_variant_order() {
+        let candidates = ["HAUS", "Haus", "haus"];
+        assert_eq!(
+            pick("HAUS", &candidates),
+            Some(("HAUS".to_string(), MatchStep::Exact))
+        );
+        assert_eq!(
+            pick("hAUs", &candidates),
+            Some(("haus".to_string(), MatchStep::Lowercase))
+        );
+
+        let candidates = ["McDonald", "Mcdonald"];
+        assert_eq!(
+            pick("MCDONALD", &candidates),
+            Some(("Mcdonald".to_string(), MatchStep::Titlecase))
+        );
+        assert_eq!(
+            pick("mcDonald", &candidates[..1]),
+            Some(("McDonald".to_string(), MatchStep::Variant))
+        );
+    }
 }
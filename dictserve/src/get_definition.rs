@@ -1,24 +1,51 @@
 use serde::{Deserialize, Serialize};
 use tracing::{debug, error, info, warn};
 
+use crate::caching::{cache_headers, etag, is_not_modified};
+use crate::dictionary::MatchStep;
 use crate::AppState;
 use axum::{
     extract::{Query, State},
-    http::StatusCode,
-    Json,
+    http::{header::CONTENT_TYPE, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
 };
 
 use crate::metrics::NoLabel;
+use crate::request_trace::{record_match_step, record_query};
 use metrics::{counter, histogram};
+use std::sync::Arc;
 use std::time::Instant;
 use Languages::TargetLanguage;
 
-use libdictdefinition::DictionaryElementData;
+use libdictdefinition::{DictionaryElementData, GrammaticalAnalysis};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct DictionaryResponse {
-    element: DictionaryElementData,
+    element: Arc<DictionaryElementData>,
     wiktionary_link: String,
+    analysis: Vec<GrammaticalAnalysis>,
+    match_step: MatchStep,
+    folded_form: Option<String>,
+}
+
+/// The entry as clients get it. Inflection tables only feed `analysis`, and would otherwise be
+/// most of the response, twice over for forms that carry their lemma. The cached entry keeps
+/// them, so it's copied only when there's a table to drop.
+fn without_forms(element: Arc<DictionaryElementData>) -> Arc<DictionaryElementData> {
+    let lemma_has_forms = element
+        .resolved_lemma
+        .as_ref()
+        .is_some_and(|lemma| !lemma.forms.is_empty());
+    if element.forms.is_empty() && !lemma_has_forms {
+        return element;
+    }
+
+    let mut element = Arc::unwrap_or_clone(element);
+    element.forms.clear();
+    if let Some(lemma) = &mut element.resolved_lemma {
+        lemma.forms.clear();
+    }
+    Arc::new(element)
 }
 
 #[derive(Deserialize, Debug)]
@@ -30,38 +57,112 @@ pub struct DictionaryRequest {
 pub async fn get_definition(
     State(state): State<AppState>,
     Query(payload): Query<DictionaryRequest>,
-) -> Result<Json<DictionaryResponse>, (StatusCode, String)> {
-    let label = [(
-        "language",
-        payload.language.to_extension_technical_format_n(),
-    )];
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, String)> {
+    let language = payload.language.to_extension_technical_format_n();
+    let label = [("language", language)];
     counter!("dictionary_query_language", &label).increment(1);
 
-    //info!("Request: {:?}", payload);
+    record_query(&state.config, &payload.language, Some(&payload.word));
 
-    let t_start = Instant::now();
-    let dict_element = state
-        .dictionary_store
-        .query(payload.language.clone(), &payload.word);
-    let t_taken = t_start.elapsed();
+    let store = state.store()?;
+    let built_at = store.stats().built_at;
+    let (lang, word) = (payload.language.clone(), payload.word.clone());
+    let (query_match, t_taken) = state
+        .lookup_pool
+        .run(move || {
+            let t_start = Instant::now();
+            let query_match = store.query(lang, &word);
+            (query_match, t_start.elapsed())
+        })
+        .await?;
 
     histogram!("dict_get_item_duration_seconds", &[] as NoLabel).record(t_taken.as_secs_f64());
 
-    match dict_element {
-        Some(element) => {
-            let label = [("status", "success")];
-            counter!("dictionary_query_status", &label).increment(1);
+    match query_match {
+        Some(query_match) => {
+            let element = query_match.element;
+            record_match_step(query_match.step.as_str());
+            let label = [("step", query_match.step.as_str()), ("language", language)];
+            counter!("dictionary_match_step", &label).increment(1);
 
-            Ok(Json(DictionaryResponse {
+            let body = serde_json::to_vec(&DictionaryResponse {
                 wiktionary_link: element.get_wiktionary_link(),
-                element,
-            }))
+                analysis: element.analysis_for(&payload.word),
+                match_step: query_match.step,
+                folded_form: query_match.folded_form,
+                element: without_forms(element),
+            })
+            .map_err(|e| {
+                error!("Failed to serialize response: {}", e);
+                (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+            })?;
+
+            // Revalidated only for a hit, so `If-None-Match: *` never hides a miss
+            let etag = etag(&body);
+            if is_not_modified(&headers, &etag) {
+                let label = [("status", "not_modified"), ("language", language)];
+                counter!("dictionary_query_status", &label).increment(1);
+
+                return Ok((
+                    StatusCode::NOT_MODIFIED,
+                    cache_headers(&state.config, etag, built_at),
+                )
+                    .into_response());
+            }
+
+            let label = [("status", "success"), ("language", language)];
+            counter!("dictionary_query_status", &label).increment(1);
+
+            Ok((
+                cache_headers(&state.config, etag, built_at),
+                [(CONTENT_TYPE, "application/json")],
+                body,
+            )
+                .into_response())
         }
         None => {
-            let label = [("language", "not_found")];
+            let label = [("status", "not_found"), ("language", language)];
             counter!("dictionary_query_status", &label).increment(1);
+            if let Some(misses) = &state.misses {
+                misses.record(&payload.language, &payload.word);
+            }
 
             Err((StatusCode::NOT_FOUND, "Word not found".to_string()))
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libdictdefinition::InflectedForm;
+
+    #[test]
+    fn test_response_drops_inflection_tables() {
+        let forms = vec![InflectedForm {
+            form: "bemerkt".to_string(),
+            analysis: GrammaticalAnalysis::from_tags(&["participle", "past"]).unwrap(),
+        }];
+        let lemma = DictionaryElementData {
+            forms: forms.clone(),
+            ..DictionaryElementData::new(TargetLanguage::German, "bemerken")
+        };
+        let element = Arc::new(DictionaryElementData {
+            forms,
+            resolved_lemma: Some(Box::new(lemma)),
+            ..DictionaryElementData::new(TargetLanguage::German, "bemerkt")
+        });
+        let analysis = element.analysis_for("bemerkt");
+
+        let sent = without_forms(element.clone());
+        assert!(sent.forms.is_empty());
+        assert!(sent.resolved_lemma.as_ref().unwrap().forms.is_empty());
+        // The cached entry still has them, so `analysis` can be computed
+        assert_eq!(element.analysis_for("bemerkt"), analysis);
+        assert_eq!(analysis.len(), 1);
+
+        let bare = Arc::new(DictionaryElementData::new(TargetLanguage::German, "und"));
+        assert!(Arc::ptr_eq(&without_forms(bare.clone()), &bare));
+    }
+}
@@ -0,0 +1,115 @@
+#[cfg(feature = "otlp")]
+use opentelemetry::trace::TracerProvider as _;
+#[cfg(feature = "otlp")]
+use opentelemetry_sdk::trace::TracerProvider;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing_loki::url::Url;
+use tracing_loki::BackgroundTaskController;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{Layer, Registry};
+
+#[cfg(feature = "otlp")]
+use crate::config::OtlpConfig;
+use crate::config::{Config, ConfigError, LogSink};
+
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// Longest wait for the Loki task to push what it has buffered.
+const LOKI_FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Sends buffered logs before exit; anything logged after `flush` may be lost.
+pub struct LogFlusher {
+    loki: Option<(BackgroundTaskController, JoinHandle<()>)>,
+    #[cfg(feature = "otlp")]
+    otlp: Option<TracerProvider>,
+}
+
+impl LogFlusher {
+    pub async fn flush(self) {
+        // Before Loki, so an export error still gets shipped
+        #[cfg(feature = "otlp")]
+        if let Some(provider) = self.otlp {
+            // Blocks until the batch exporter has sent what it queued
+            let _ = tokio::task::spawn_blocking(move || provider.shutdown()).await;
+        }
+        if let Some((controller, task)) = self.loki {
+            controller.shutdown().await;
+            let _ = tokio::time::timeout(LOKI_FLUSH_TIMEOUT, task).await;
+        }
+    }
+}
+
+/// Installs one layer per configured sink. Must run inside the runtime: the Loki sink ships logs
+/// from a spawned task.
+pub fn init(config: &Config) -> Result<LogFlusher, ConfigError> {
+    let level = config.log_level_filter()?;
+    let mut layers: Vec<BoxedLayer> = Vec::new();
+    let mut flusher = LogFlusher {
+        loki: None,
+        #[cfg(feature = "otlp")]
+        otlp: None,
+    };
+
+    for sink in &config.log_sinks {
+        let layer = match sink {
+            LogSink::Pretty => tracing_subscriber::fmt::layer().pretty().boxed(),
+            LogSink::Json => tracing_subscriber::fmt::layer().json().boxed(),
+            LogSink::Loki => {
+                let loki = config.loki.as_ref().ok_or_else(|| {
+                    ConfigError::invalid("log_sinks", "the loki sink needs a [loki] section")
+                })?;
+                let url = Url::parse(&loki.url)
+                    .map_err(|e| ConfigError::invalid("loki.url", e.to_string()))?;
+
+                let (layer, controller, task) = tracing_loki::builder()
+                    .label("job", &loki.job)
+                    .and_then(|builder| builder.build_controller_url(url))
+                    .map_err(|e| ConfigError::invalid("loki", e.to_string()))?;
+                flusher.loki = Some((controller, tokio::spawn(task)));
+                layer.boxed()
+            }
+        };
+        layers.push(layer.with_filter(level).boxed());
+    }
+
+    // Spans pass the same level filter as logs, so `log_level = "warn"` exports none
+    #[cfg(feature = "otlp")]
+    if let Some(otlp) = &config.otlp {
+        let provider = otlp_tracer_provider(otlp)?;
+        let tracer = provider.tracer("dictserve");
+        layers.push(
+            tracing_opentelemetry::layer()
+                .with_tracer(tracer)
+                .with_filter(level)
+                .boxed(),
+        );
+        flusher.otlp = Some(provider);
+    }
+
+    tracing_subscriber::registry().with(layers).init();
+    Ok(flusher)
+}
+
+#[cfg(feature = "otlp")]
+fn otlp_tracer_provider(otlp: &OtlpConfig) -> Result<TracerProvider, ConfigError> {
+    use opentelemetry::KeyValue;
+    use opentelemetry_otlp::WithExportConfig;
+
+    let resource = opentelemetry_sdk::Resource::new(vec![KeyValue::new(
+        "service.name",
+        otlp.service_name.clone(),
+    )]);
+
+    opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(&otlp.endpoint),
+        )
+        .with_trace_config(opentelemetry_sdk::trace::Config::default().with_resource(resource))
+        .install_batch(opentelemetry_sdk::runtime::Tokio)
+        .map_err(|e| ConfigError::invalid("otlp", e.to_string()))
+}
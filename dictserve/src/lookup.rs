@@ -0,0 +1,93 @@
+use axum::http::StatusCode;
+use metrics::{counter, histogram};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tokio::time::timeout;
+use tracing::Span;
+
+use crate::metrics::NoLabel;
+
+/// Runs dictionary lookups on Tokio's blocking threads so decompression never stalls the
+/// executor. At most `concurrency` lookups run at once; the rest wait for a permit, and a request
+/// that hasn't finished within `timeout` gets a 503.
+#[derive(Clone)]
+pub struct LookupPool {
+    permits: Arc<Semaphore>,
+    timeout: Duration,
+}
+
+impl LookupPool {
+    pub fn new(concurrency: usize, timeout: Duration) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(concurrency)),
+            timeout,
+        }
+    }
+
+    pub async fn run<T, F>(&self, lookup: F) -> Result<T, (StatusCode, String)>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let t_start = Instant::now();
+
+        let permit = match timeout(self.timeout, self.permits.clone().acquire_owned()).await {
+            Ok(permit) => permit.expect("lookup semaphore is never closed"),
+            Err(_) => return Err(Self::timed_out("queued")),
+        };
+        let queued = t_start.elapsed();
+        histogram!("lookup_queue_seconds", &[] as NoLabel).record(queued.as_secs_f64());
+
+        // The permit moves into the task, so a lookup that outlives its request still counts
+        // against the limit until it finishes. Entering the request's span keeps the lookup's
+        // logs attached to it
+        let span = Span::current();
+        let task = tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            let _entered = span.enter();
+            lookup()
+        });
+
+        match timeout(self.timeout.saturating_sub(queued), task).await {
+            Ok(Ok(result)) => Ok(result),
+            Ok(Err(e)) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Lookup failed: {}", e),
+            )),
+            Err(_) => Err(Self::timed_out("running")),
+        }
+    }
+
+    fn timed_out(stage: &'static str) -> (StatusCode, String) {
+        counter!("lookup_timeouts", &[("stage", stage)]).increment(1);
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Lookup timed out, try again later".to_string(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_lookup_returns_result() {
+        let pool = LookupPool::new(2, Duration::from_secs(1));
+        assert_eq!(pool.run(|| 2 + 2).await.unwrap(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_slow_lookup_times_out() {
+        let pool = LookupPool::new(1, Duration::from_millis(20));
+        let result = pool
+            .run(|| std::thread::sleep(Duration::from_millis(200)))
+            .await;
+        assert_eq!(result.unwrap_err().0, StatusCode::SERVICE_UNAVAILABLE);
+
+        // The abandoned lookup still holds the only permit, so this one times out queueing
+        let result = pool.run(|| ()).await;
+        assert_eq!(result.unwrap_err().0, StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
@@ -1,81 +1,149 @@
+mod annotate;
+mod auth;
+mod backlinks;
+mod caching;
+mod canary;
 mod config;
+mod cors;
 mod dictionary;
 mod get_definition;
+mod logging;
+mod lookup;
 mod metrics;
+mod misses;
+mod reload;
+mod request_trace;
+mod shutdown;
+mod status;
+use auth::Auth;
 use config::Config;
 use dictionary::DictionaryStore;
+use lookup::LookupPool;
+use misses::MissTracker;
+use reload::{LoadedDump, StoreSlot};
+use shutdown::Shutdown;
 
+use axum::middleware;
 use axum::routing::{get, post};
 use axum::Router;
 
 use tokio::net::TcpListener;
 
-use tracing::{debug, error, info};
-use tracing_subscriber::layer::SubscriberExt;
-use tracing_subscriber::util::SubscriberInitExt;
-use tracing_subscriber::Layer;
+use axum::http::StatusCode;
+use tracing::{debug, info, warn};
 
-use tracing::level_filters::LevelFilter;
-use tracing_loki::url::Url;
-use tracing_subscriber::prelude::*;
-
-use std::sync::Arc;
+use std::future::IntoFuture;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 
 #[derive(Clone)]
 pub struct AppState {
     config: Config,
-    dictionary_store: Arc<DictionaryStore>,
+    /// Empty until the dump has loaded; the listener is bound before that so probes can tell
+    /// loading from dead.
+    dictionary_store: StoreSlot,
+    lookup_pool: LookupPool,
+    auth: Arc<Auth>,
+    /// Set when `[misses]` is configured.
+    misses: Option<Arc<MissTracker>>,
+    started_at: Instant,
 }
 
-#[tokio::main]
-async fn main() {
-    let config = Config::from_file("./Config/config.toml").unwrap();
-    let cloned_conf = config.clone();
-
-    let mut labels_map = std::collections::HashMap::new();
-    labels_map.insert("job".to_string(), config.loki_job.clone());
-
-    let empty_map = std::collections::HashMap::new();
-
-    let (loki_layer, task) =
-        tracing_loki::layer(Url::parse(&config.loki_url).unwrap(), labels_map, empty_map).unwrap();
-
-    let loki_layer = loki_layer.with_filter(LevelFilter::INFO);
-
-    let console_layer = tracing_subscriber::fmt::layer()
-        .pretty()
-        .with_filter(LevelFilter::INFO);
+impl AppState {
+    pub fn loaded(&self) -> Option<LoadedDump> {
+        self.dictionary_store.read().unwrap().clone()
+    }
+
+    /// The loaded store, or a 503 while the dump is still loading.
+    pub fn store(&self) -> Result<Arc<DictionaryStore>, (StatusCode, String)> {
+        self.loaded().map(|loaded| loaded.store).ok_or((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Dictionary is still loading".to_string(),
+        ))
+    }
+}
 
-    tracing_subscriber::registry()
-        .with(loki_layer)
-        .with(console_layer)
-        .init();
+fn main() {
+    let runtime = tokio::runtime::Runtime::new().expect("Failed to start the Tokio runtime");
+    runtime.block_on(run());
+    // A dump still loading, or a lookup abandoned by its timeout, would otherwise hold up exit
+    runtime.shutdown_timeout(Duration::from_secs(1));
+}
 
-    let _ = tokio::spawn(task);
+async fn run() {
+    let (config, auth, log_flusher) = config::path_from_args(std::env::args().skip(1))
+        .and_then(|path| Config::load(&path))
+        .and_then(|config| Auth::from_config(&config).map(|auth| (config, auth)))
+        .and_then(|(config, auth)| logging::init(&config).map(|flusher| (config, auth, flusher)))
+        .unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        });
+    for deprecation in &config.deprecations {
+        warn!("{}", deprecation);
+    }
+    let cloned_conf = config.clone();
 
-    info!("Creating in-memory dictionary...");
-    let dict_store = dictionary::DictionaryStore::from_elements_dump(&config.dump_path);
+    let misses = config
+        .misses
+        .clone()
+        .map(|misses| Arc::new(MissTracker::new(misses)));
+
+    let state = AppState {
+        config: cloned_conf,
+        dictionary_store: Arc::new(RwLock::new(None)),
+        lookup_pool: LookupPool::new(
+            config.lookup_concurrency,
+            Duration::from_millis(config.lookup_timeout_ms),
+        ),
+        auth: Arc::new(auth),
+        misses: misses.clone(),
+        started_at: Instant::now(),
+    };
+    let store_slot = state.dictionary_store.clone();
 
     let app = Router::new()
         .route("/get_definition", get(get_definition::get_definition))
-        .with_state(AppState {
-            config: cloned_conf,
-            dictionary_store: Arc::new(dict_store.unwrap()),
-        });
+        .route("/annotate", post(annotate::annotate))
+        .route("/backlinks", get(backlinks::get_backlinks))
+        .layer(middleware::from_fn(metrics::record_response_size))
+        .layer(middleware::from_fn_with_state(state.clone(), auth::guard))
+        .layer(cors::cors_layer(&config).expect("Invalid CORS config"));
+    // Probes stay outside the tracing so they don't flood the logs
+    let app = request_trace::traced(app)
+        .route("/healthz", get(status::healthz))
+        .route("/readyz", get(status::readyz))
+        .route("/stats", get(status::stats))
+        .with_state(state);
 
     debug!("App initialised");
 
-    let metrics_listener = TcpListener::bind(config.metrics_bind.clone())
-        .await
-        .expect("Failed to bind metrics server");
+    let shutdown = Shutdown::listen();
 
-    tokio::spawn(async move {
-        axum::serve(metrics_listener, metrics::metrics_app())
+    let mut metrics_server = None;
+    if let Some(metrics_config) = &config.metrics {
+        let metrics_listener = TcpListener::bind(metrics_config.bind.clone())
             .await
-            .unwrap();
-    });
-
-    info!("Metrics server started on {}", config.metrics_bind);
+            .expect("Failed to bind metrics server");
+
+        let mut metrics_app = metrics::metrics_app(&metrics_config.buckets);
+        if let Some(misses) = &misses {
+            metrics_app = metrics_app.merge(
+                Router::new()
+                    .route("/admin/top_misses", get(misses::top_misses))
+                    .with_state(misses.clone()),
+            );
+        }
+
+        metrics_server = Some(tokio::spawn(
+            axum::serve(metrics_listener, metrics_app)
+                .with_graceful_shutdown(shutdown.clone().requested())
+                .into_future(),
+        ));
+
+        info!("Metrics server started on {}", metrics_config.bind);
+    }
 
     let listener = TcpListener::bind(format!("{}:{}", config.listen_address, config.listen_port))
         .await
@@ -86,7 +154,40 @@ async fn main() {
         config.listen_address, config.listen_port
     );
 
-    axum::serve(listener, app)
-        .await
-        .expect("Failed to start serving");
+    reload::spawn_initial_load(store_slot.clone(), config.clone());
+    reload::spawn_reload_on_hangup(store_slot, config.clone());
+    if let Some(misses) = &misses {
+        misses.clone().spawn_periodic_export();
+    }
+
+    let api_server = tokio::spawn(
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(shutdown.clone().requested())
+        .into_future(),
+    );
+
+    // Both servers stop accepting as soon as the signal arrives; this waits for the requests
+    // they already accepted
+    shutdown.requested().await;
+    let drained = async {
+        let _ = api_server.await;
+        if let Some(metrics_server) = metrics_server {
+            let _ = metrics_server.await;
+        }
+    };
+    match tokio::time::timeout(Duration::from_secs(config.shutdown_timeout_secs), drained).await {
+        Ok(()) => info!("In-flight requests finished, exiting"),
+        Err(_) => warn!(
+            "Requests still in flight after {}s, exiting anyway",
+            config.shutdown_timeout_secs
+        ),
+    }
+
+    if let Some(misses) = misses {
+        let _ = tokio::task::spawn_blocking(move || misses.export()).await;
+    }
+    log_flusher.flush().await;
 }
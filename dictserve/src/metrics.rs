@@ -1,45 +1,79 @@
 // src/metrics.rs
 
-use axum::{routing::get, Router};
+use axum::{
+    body::HttpBody,
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+    routing::get,
+    Router,
+};
+use metrics::histogram;
 use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
-use std::sync::Arc;
-use std::sync::Once;
-use tokio::sync::Mutex;
-
-static INIT: Once = Once::new();
-static mut RECORDER_HANDLE: Option<Arc<Mutex<PrometheusHandle>>> = None;
-
-pub fn setup_metrics_recorder() -> Arc<Mutex<PrometheusHandle>> {
-    INIT.call_once(|| {
-        const DICT_HIST: &[f64] = &[0.000001, 0.00001, 0.0001, 0.001, 0.01, 0.1, 1.0];
-        const DB_HIST: &[f64] = &[0.001, 0.01, 0.1, 1.0, 10.0];
-
-        let recorder_handle = PrometheusBuilder::new()
-            .set_buckets_for_metric(
-                Matcher::Full("dict_get_item_duration_seconds".to_string()),
-                DICT_HIST,
-            )
-            .unwrap()
-            .install_recorder()
-            .unwrap();
-
-        unsafe {
-            RECORDER_HANDLE = Some(Arc::new(Mutex::new(recorder_handle)));
-        }
-    });
-
-    unsafe { RECORDER_HANDLE.clone().unwrap() }
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+static RECORDER_HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+
+const LATENCY_BUCKETS: &[f64] = &[0.000001, 0.00001, 0.0001, 0.001, 0.01, 0.1, 1.0];
+const SIZE_BUCKETS: &[f64] = &[256.0, 1024.0, 4096.0, 16384.0, 65536.0, 262144.0, 1048576.0];
+
+/// Bucket boundaries for every histogram; `[metrics.buckets]` in the config replaces any of them.
+const DEFAULT_BUCKETS: &[(&str, &[f64])] = &[
+    ("dict_get_item_duration_seconds", LATENCY_BUCKETS),
+    ("dictionary_index_lookup_duration_seconds", LATENCY_BUCKETS),
+    ("dictionary_decompress_duration_seconds", LATENCY_BUCKETS),
+    ("lookup_queue_seconds", LATENCY_BUCKETS),
+    ("annotate_duration_seconds", LATENCY_BUCKETS),
+    ("dictionary_decompressed_bytes", SIZE_BUCKETS),
+    ("response_size_bytes", SIZE_BUCKETS),
+];
+
+/// Installs the Prometheus recorder on first call; later calls return the same handle and
+/// ignore `buckets`.
+pub fn setup_metrics_recorder(buckets: &HashMap<String, Vec<f64>>) -> PrometheusHandle {
+    RECORDER_HANDLE
+        .get_or_init(|| {
+            let mut builder = PrometheusBuilder::new();
+            for (name, defaults) in DEFAULT_BUCKETS {
+                if !buckets.contains_key(*name) {
+                    builder = builder
+                        .set_buckets_for_metric(Matcher::Full(name.to_string()), defaults)
+                        .unwrap();
+                }
+            }
+            for (name, values) in buckets {
+                builder = builder
+                    .set_buckets_for_metric(Matcher::Full(name.clone()), values)
+                    .unwrap();
+            }
+
+            builder.install_recorder().unwrap()
+        })
+        .clone()
 }
 
-pub fn metrics_app() -> Router {
-    let recorder_handle = setup_metrics_recorder();
+pub fn metrics_app(buckets: &HashMap<String, Vec<f64>>) -> Router {
+    let recorder_handle = setup_metrics_recorder(buckets);
     Router::new().route(
         "/metrics",
-        get(move || async move {
-            let handle = recorder_handle.lock().await;
-            handle.render()
-        }),
+        get(move || async move { recorder_handle.render() }),
     )
 }
 
+/// Records body sizes per route. JSON bodies are buffered, so their size is known up front;
+/// streamed bodies are skipped.
+pub async fn record_response_size(request: Request, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or("unmatched".to_string(), |path| path.as_str().to_string());
+
+    let response = next.run(request).await;
+    if let Some(size) = response.body().size_hint().exact() {
+        histogram!("response_size_bytes", &[("route", route)]).record(size as f64);
+    }
+    response
+}
+
 pub type NoLabel = &'static [(&'static str, &'static str)];
@@ -0,0 +1,381 @@
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use libdictdefinition::normalize::normalize_key;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+use Languages::TargetLanguage;
+
+use crate::auth::API_KEY_HEADER;
+
+const DEFAULT_LIMIT: usize = 50;
+
+/// `[misses]`: opt-in tracking of words `/get_definition` couldn't find.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct MissConfig {
+    /// Words tracked per language; the rarest are evicted to make room.
+    #[serde(default = "default_capacity")]
+    pub capacity: usize,
+    /// Record one miss in this many.
+    #[serde(default = "default_sample_every")]
+    pub sample_every: u64,
+    /// Longer queries are pasted text rather than words, and are never recorded.
+    #[serde(default = "default_max_word_chars")]
+    pub max_word_chars: usize,
+    /// Words missed fewer times than this are never reported or exported.
+    #[serde(default = "default_min_report_count")]
+    pub min_report_count: u64,
+    /// JSON file rewritten every `export_interval_secs` and at shutdown.
+    #[serde(default)]
+    pub export_path: Option<String>,
+    #[serde(default = "default_export_interval_secs")]
+    pub export_interval_secs: u64,
+    /// `/admin/top_misses` answers only requests sending this in `X-Api-Key`. Required unless
+    /// `metrics.bind` is a loopback address; `DICTSERVE_MISSES__ADMIN_KEY` keeps it out of the file.
+    #[serde(default)]
+    pub admin_key: Option<String>,
+}
+
+fn default_capacity() -> usize {
+    1_000
+}
+
+fn default_sample_every() -> u64 {
+    1
+}
+
+fn default_max_word_chars() -> usize {
+    40
+}
+
+fn default_min_report_count() -> u64 {
+    3
+}
+
+fn default_export_interval_secs() -> u64 {
+    3_600
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MissedWord {
+    pub word: String,
+    /// Sampled misses; overestimates by at most `error`.
+    pub count: u64,
+    pub error: u64,
+}
+
+/// Space-Saving heavy hitters: any word missed more than total / capacity times is guaranteed
+/// to be tracked, in fixed memory.
+struct SpaceSaving {
+    capacity: usize,
+    /// Word → (count, error).
+    counts: HashMap<Arc<str>, (u64, u64)>,
+    /// The same words ordered by count, so the one to evict is found in O(log capacity).
+    by_count: BTreeSet<(u64, Arc<str>)>,
+}
+
+impl SpaceSaving {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            counts: HashMap::with_capacity(capacity),
+            by_count: BTreeSet::new(),
+        }
+    }
+
+    fn record(&mut self, word: &str) {
+        if let Some((word, &(count, error))) = self.counts.get_key_value(word) {
+            let word = word.clone();
+            self.by_count.remove(&(count, word.clone()));
+            self.by_count.insert((count + 1, word.clone()));
+            self.counts.insert(word, (count + 1, error));
+            return;
+        }
+
+        let (count, error) = if self.counts.len() < self.capacity {
+            (1, 0)
+        } else {
+            // The newcomer inherits the evicted word's count, which bounds its overestimate
+            let Some((min_count, evicted)) = self.by_count.pop_first() else {
+                return;
+            };
+            self.counts.remove(&evicted);
+            (min_count + 1, min_count)
+        };
+        let word: Arc<str> = word.into();
+        self.by_count.insert((count, word.clone()));
+        self.counts.insert(word, (count, error));
+    }
+
+    fn top(&self, min_count: u64, limit: usize) -> Vec<MissedWord> {
+        let mut words: Vec<MissedWord> = self
+            .counts
+            .iter()
+            .filter(|(_, (count, _))| *count >= min_count)
+            .map(|(word, (count, error))| MissedWord {
+                word: word.to_string(),
+                count: *count,
+                error: *error,
+            })
+            .collect();
+        words.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.word.cmp(&b.word)));
+        words.truncate(limit);
+        words
+    }
+}
+
+pub struct MissTracker {
+    config: MissConfig,
+    seen: AtomicU64,
+    languages: Mutex<HashMap<TargetLanguage, SpaceSaving>>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct MissExport {
+    exported_at: u64,
+    sample_every: u64,
+    languages: HashMap<TargetLanguage, Vec<MissedWord>>,
+}
+
+impl MissTracker {
+    pub fn new(config: MissConfig) -> Self {
+        Self {
+            config,
+            seen: AtomicU64::new(0),
+            languages: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn record(&self, lang: &TargetLanguage, word: &str) {
+        if word.chars().count() > self.config.max_word_chars {
+            return;
+        }
+        let seen = self.seen.fetch_add(1, Ordering::Relaxed);
+        if !seen.is_multiple_of(self.config.sample_every) {
+            return;
+        }
+
+        // Case variants of one word are one gap
+        let word = normalize_key(word.trim());
+        let mut languages = self.languages.lock().unwrap();
+        languages
+            .entry(lang.clone())
+            .or_insert_with(|| SpaceSaving::new(self.config.capacity))
+            .record(&word);
+    }
+
+    pub fn top(&self, lang: &TargetLanguage, limit: usize) -> Vec<MissedWord> {
+        self.languages
+            .lock()
+            .unwrap()
+            .get(lang)
+            .map(|tracked| tracked.top(self.config.min_report_count, limit))
+            .unwrap_or_default()
+    }
+
+    fn snapshot(&self) -> MissExport {
+        let languages = self
+            .languages
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(lang, tracked)| {
+                (
+                    lang.clone(),
+                    tracked.top(self.config.min_report_count, self.config.capacity),
+                )
+            })
+            .collect();
+
+        MissExport {
+            exported_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            sample_every: self.config.sample_every,
+            languages,
+        }
+    }
+
+    /// Writes every language's report to `export_path`, replacing the previous export atomically.
+    pub fn export(&self) {
+        let Some(path) = &self.config.export_path else {
+            return;
+        };
+
+        let tmp_path = format!("{}.tmp", path);
+        let written = serde_json::to_vec_pretty(&self.snapshot())
+            .map_err(std::io::Error::from)
+            .and_then(|json| std::fs::write(&tmp_path, json))
+            .and_then(|_| std::fs::rename(&tmp_path, path));
+
+        match written {
+            Ok(()) => info!("Exported missed words to {}", path),
+            Err(e) => warn!("Failed to export missed words to {}: {}", path, e),
+        }
+    }
+
+    pub fn spawn_periodic_export(self: Arc<Self>) {
+        if self.config.export_path.is_none() {
+            return;
+        }
+
+        let period = Duration::from_secs(self.config.export_interval_secs);
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            loop {
+                interval.tick().await;
+                let tracker = self.clone();
+                let _ = tokio::task::spawn_blocking(move || tracker.export()).await;
+            }
+        });
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TopMissesRequest {
+    language: TargetLanguage,
+    limit: Option<usize>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TopMissesResponse {
+    sample_every: u64,
+    words: Vec<MissedWord>,
+}
+
+/// Admin route, served on the metrics listener rather than the public API.
+pub async fn top_misses(
+    State(tracker): State<Arc<MissTracker>>,
+    Query(payload): Query<TopMissesRequest>,
+    headers: HeaderMap,
+) -> Result<Json<TopMissesResponse>, (StatusCode, String)> {
+    if let Some(admin_key) = &tracker.config.admin_key {
+        let sent = headers.get(API_KEY_HEADER).and_then(|v| v.to_str().ok());
+        if sent != Some(admin_key.as_str()) {
+            return Err((StatusCode::UNAUTHORIZED, "Admin key required".to_string()));
+        }
+    }
+
+    let limit = payload
+        .limit
+        .unwrap_or(DEFAULT_LIMIT)
+        .min(tracker.config.capacity);
+
+    Ok(Json(TopMissesResponse {
+        sample_every: tracker.config.sample_every,
+        words: tracker.top(&payload.language, limit),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> MissConfig {
+        toml::from_str("").unwrap()
+    }
+
+    fn words(missed: &[MissedWord]) -> Vec<(&str, u64)> {
+        missed.iter().map(|m| (m.word.as_str(), m.count)).collect()
+    }
+
+    #[test]
+    fn test_space_saving_keeps_heavy_hitters() {
+        let mut tracked = SpaceSaving::new(2);
+        for word in ["a", "a", "a", "b", "c", "a", "d"] {
+            tracked.record(word);
+        }
+
+        let top = tracked.top(0, 10);
+        assert_eq!(top[0].word, "a");
+        assert_eq!((top[0].count, top[0].error), (4, 0));
+        // "d" replaced the rarer word and inherited its count as error
+        assert_eq!(top[1].word, "d");
+        assert_eq!(top[1].count - top[1].error, 1);
+    }
+
+    #[test]
+    fn test_space_saving_evicts_the_rarest() {
+        let mut tracked = SpaceSaving::new(3);
+        for word in ["a", "a", "b", "b", "b", "c", "d", "d"] {
+            tracked.record(word);
+        }
+
+        // "d" took "c"'s place, and counts and order agree after every step
+        assert_eq!(
+            words(&tracked.top(0, 10)),
+            vec![("b", 3), ("d", 3), ("a", 2)]
+        );
+        assert_eq!(tracked.by_count.len(), tracked.counts.len());
+        assert!(tracked
+            .by_count
+            .iter()
+            .all(|(count, word)| tracked.counts[word].0 == *count));
+    }
+
+    #[tokio::test]
+    async fn test_top_misses_needs_the_admin_key() {
+        let tracker = Arc::new(MissTracker::new(MissConfig {
+            admin_key: Some("secret".to_string()),
+            ..config()
+        }));
+        let query = || {
+            Query(TopMissesRequest {
+                language: TargetLanguage::German,
+                limit: None,
+            })
+        };
+        let mut headers = HeaderMap::new();
+
+        let refused = top_misses(State(tracker.clone()), query(), headers.clone()).await;
+        assert_eq!(refused.unwrap_err().0, StatusCode::UNAUTHORIZED);
+
+        headers.insert(API_KEY_HEADER, "secret".parse().unwrap());
+        assert!(top_misses(State(tracker), query(), headers).await.is_ok());
+    }
+
+    #[test]
+    fn test_tracker_groups_case_and_respects_privacy_limits() {
+        let tracker = MissTracker::new(MissConfig {
+            min_report_count: 2,
+            max_word_chars: 10,
+            ..config()
+        });
+        let german = TargetLanguage::German;
+
+        tracker.record(&german, "Gehts");
+        tracker.record(&german, "gehts");
+        tracker.record(&german, "once");
+        for _ in 0..5 {
+            tracker.record(&german, "a sentence far too long to be a word");
+        }
+
+        assert_eq!(words(&tracker.top(&german, 10)), vec![("gehts", 2)]);
+        assert!(tracker.top(&TargetLanguage::French, 10).is_empty());
+    }
+
+    #[test]
+    fn test_tracker_samples() {
+        let tracker = MissTracker::new(MissConfig {
+            sample_every: 3,
+            min_report_count: 1,
+            ..config()
+        });
+        for _ in 0..9 {
+            tracker.record(&TargetLanguage::German, "x");
+        }
+
+        assert_eq!(
+            words(&tracker.top(&TargetLanguage::German, 10)),
+            vec![("x", 3)]
+        );
+    }
+}
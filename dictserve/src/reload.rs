@@ -0,0 +1,97 @@
+use metrics::counter;
+use std::sync::{Arc, RwLock};
+use tokio::signal::unix::{signal, SignalKind};
+use tracing::{error, info, warn};
+
+use crate::canary::{self, CanaryFailure};
+use crate::config::Config;
+use crate::dictionary::DictionaryStore;
+
+/// The store requests are served from: empty until the first load, replaced on reload.
+pub type StoreSlot = Arc<RwLock<Option<LoadedDump>>>;
+
+/// A store with the canary results it was loaded with. Stores never change after loading, so
+/// neither do the results, and `/readyz` reports them without looking anything up.
+#[derive(Clone)]
+pub struct LoadedDump {
+    pub store: Arc<DictionaryStore>,
+    pub canary_failures: Arc<[CanaryFailure]>,
+}
+
+fn load(config: &Config) -> std::io::Result<LoadedDump> {
+    info!("Creating in-memory dictionary...");
+    let store = DictionaryStore::from_elements_dump(&config.dump_path, config.cache_capacity)?;
+    let failures = canary::check(&store, &config.canaries);
+    for failure in &failures {
+        error!("Canary failed: {}", failure);
+    }
+    Ok(LoadedDump {
+        store: Arc::new(store),
+        canary_failures: failures.into(),
+    })
+}
+
+/// A dump that can't be read at startup is fatal. One whose canaries fail is still installed,
+/// so `/stats` shows what was loaded, but `/readyz` keeps reporting not ready.
+pub fn spawn_initial_load(slot: StoreSlot, config: Config) {
+    tokio::task::spawn_blocking(move || match load(&config) {
+        Ok(loaded) => {
+            let failed = loaded.canary_failures.len();
+            *slot.write().unwrap() = Some(loaded);
+            if failed == 0 {
+                info!("Dictionary loaded, serving lookups");
+            } else {
+                error!("Dictionary loaded, but {} canaries failed", failed);
+            }
+        }
+        Err(e) => {
+            error!("Failed to load dump {}: {}", config.dump_path, e);
+            std::process::exit(1);
+        }
+    });
+}
+
+/// On SIGHUP, loads the dump again and swaps it in only if it loads and every canary passes;
+/// otherwise the current dump keeps serving. Both dumps are in memory while the new one loads.
+pub fn spawn_reload_on_hangup(slot: StoreSlot, config: Config) {
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(e) => {
+            warn!("Cannot listen for SIGHUP, hot reload disabled: {}", e);
+            return;
+        }
+    };
+
+    tokio::spawn(async move {
+        while hangups.recv().await.is_some() {
+            info!("SIGHUP received, reloading {}", config.dump_path);
+
+            let reload_config = config.clone();
+            let loaded = tokio::task::spawn_blocking(move || load(&reload_config)).await;
+
+            let result = match loaded {
+                Ok(Ok(loaded)) if loaded.canary_failures.is_empty() => {
+                    *slot.write().unwrap() = Some(loaded);
+                    info!("Reloaded dictionary");
+                    "swapped"
+                }
+                Ok(Ok(loaded)) => {
+                    error!(
+                        "Refusing to reload: {} canaries failed, keeping the current dump",
+                        loaded.canary_failures.len()
+                    );
+                    "canary_failed"
+                }
+                Ok(Err(e)) => {
+                    error!("Refusing to reload, cannot load dump: {}", e);
+                    "load_failed"
+                }
+                Err(e) => {
+                    error!("Refusing to reload, loading panicked: {}", e);
+                    "load_failed"
+                }
+            };
+            counter!("dump_reloads", &[("result", result)]).increment(1);
+        }
+    });
+}
@@ -0,0 +1,145 @@
+use axum::{
+    extract::{MatchedPath, Request},
+    http::{HeaderName, Response},
+    middleware, Router,
+};
+use libdictdefinition::normalize::normalize_key;
+use std::time::Duration;
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+use tower_http::trace::TraceLayer;
+use tracing::{debug, field::Empty, info_span, Span};
+use Languages::TargetLanguage;
+
+use crate::config::Config;
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+/// Longest caller-supplied ID kept; a UUID is 36.
+const MAX_REQUEST_ID_LEN: usize = 64;
+
+/// Gives every request an `X-Request-Id`, keeping the caller's if it's a plausible ID, and runs it
+/// in a `request` span carrying that ID. The ID is echoed on the response so clients can quote it.
+pub fn traced<S>(router: Router<S>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    router
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(make_span)
+                .on_response(on_response),
+        )
+        .layer(PropagateRequestIdLayer::new(REQUEST_ID_HEADER))
+        .layer(SetRequestIdLayer::new(REQUEST_ID_HEADER, MakeRequestUuid))
+        .layer(middleware::map_request(drop_unsafe_request_id))
+}
+
+/// Removes an `X-Request-Id` that's too long or has characters other than letters, digits and
+/// `-_.`, so a fresh one is generated instead. The ID goes into every log line and back to the
+/// client, and callers control it.
+async fn drop_unsafe_request_id(mut request: Request) -> Request {
+    let safe = request.headers().get(REQUEST_ID_HEADER).map(|id| {
+        let id = id.as_bytes();
+        !id.is_empty()
+            && id.len() <= MAX_REQUEST_ID_LEN
+            && id
+                .iter()
+                .all(|b| b.is_ascii_alphanumeric() || b"-_.".contains(b))
+    });
+    if safe == Some(false) {
+        request.headers_mut().remove(REQUEST_ID_HEADER);
+    }
+    request
+}
+
+fn make_span<B>(request: &Request<B>) -> Span {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|id| id.to_str().ok())
+        .unwrap_or_default();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or("unmatched", |path| path.as_str());
+
+    // Handlers fill in what they know with `record_query` and `record_match_step`
+    info_span!(
+        "request",
+        request_id,
+        method = %request.method(),
+        route,
+        language = Empty,
+        word = Empty,
+        match_step = Empty,
+        status = Empty,
+        latency_ms = Empty,
+    )
+}
+
+fn on_response<B>(response: &Response<B>, latency: Duration, span: &Span) {
+    span.record("status", response.status().as_u16());
+    span.record("latency_ms", latency.as_secs_f64() * 1000.0);
+    debug!("Request finished");
+}
+
+/// Adds the queried language, and with `log_query_words` the normalized word, to the current
+/// request span.
+pub fn record_query(config: &Config, lang: &TargetLanguage, word: Option<&str>) {
+    let span = Span::current();
+    span.record("language", lang.to_extension_technical_format_n());
+    if let Some(word) = word.filter(|_| config.log_query_words) {
+        span.record("word", normalize_key(word).as_str());
+    }
+}
+
+pub fn record_match_step(step: &str) {
+    Span::current().record("match_step", step);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::routing::get;
+    use tower::ServiceExt;
+
+    async fn request_id(sent: Option<&str>) -> String {
+        let app = traced(Router::new().route("/", get(|| async { "ok" })));
+        let mut request = Request::get("/");
+        if let Some(sent) = sent {
+            request = request.header(REQUEST_ID_HEADER, sent);
+        }
+
+        let response = app
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        response.headers()[REQUEST_ID_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_request_id_is_generated() {
+        let first = request_id(None).await;
+        let second = request_id(None).await;
+
+        assert_eq!(first.len(), 36);
+        assert_ne!(first, second);
+    }
+
+    #[tokio::test]
+    async fn test_request_id_is_propagated() {
+        assert_eq!(request_id(Some("abc-123")).await, "abc-123");
+    }
+
+    #[tokio::test]
+    async fn test_unsafe_request_id_is_replaced() {
+        for sent in ["a b", "id\"}{", &"x".repeat(MAX_REQUEST_ID_LEN + 1)] {
+            let id = request_id(Some(sent)).await;
+            assert_ne!(id, sent);
+            assert_eq!(id.len(), 36);
+        }
+    }
+}
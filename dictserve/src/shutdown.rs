@@ -0,0 +1,33 @@
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::watch;
+use tracing::info;
+
+/// Fans a SIGTERM or SIGINT out to everything that needs to stop: each server waits on its own
+/// clone.
+#[derive(Clone)]
+pub struct Shutdown {
+    receiver: watch::Receiver<bool>,
+}
+
+impl Shutdown {
+    pub fn listen() -> Self {
+        let (sender, receiver) = watch::channel(false);
+
+        tokio::spawn(async move {
+            let mut terminate =
+                signal(SignalKind::terminate()).expect("Failed to listen for SIGTERM");
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => info!("SIGINT received, shutting down"),
+                _ = terminate.recv() => info!("SIGTERM received, shutting down"),
+            }
+            let _ = sender.send(true);
+        });
+
+        Self { receiver }
+    }
+
+    pub async fn requested(mut self) {
+        // Only errors if the sender is dropped without signalling, i.e. never
+        let _ = self.receiver.wait_for(|&requested| requested).await;
+    }
+}
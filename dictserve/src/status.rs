@@ -0,0 +1,81 @@
+use axum::{extract::State, http::StatusCode, Json};
+use serde::Serialize;
+
+use crate::dictionary::StoreStats;
+use crate::AppState;
+
+/// Liveness: answers as soon as the listener is up, whether or not the dump has loaded.
+pub async fn healthz() -> &'static str {
+    "ok"
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Readiness {
+    Loading,
+    CanaryFailed,
+    Ready,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ReadinessResponse {
+    status: Readiness,
+    /// "language:word: problem" for each configured canary that fails.
+    failed_canaries: Vec<String>,
+}
+
+pub async fn readyz(State(state): State<AppState>) -> (StatusCode, Json<ReadinessResponse>) {
+    let loaded = match state.loaded() {
+        Some(loaded) => loaded,
+        None => {
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(ReadinessResponse {
+                    status: Readiness::Loading,
+                    failed_canaries: Vec::new(),
+                }),
+            )
+        }
+    };
+
+    let failed_canaries: Vec<String> = loaded
+        .canary_failures
+        .iter()
+        .map(|failure| failure.to_string())
+        .collect();
+    if failed_canaries.is_empty() {
+        (
+            StatusCode::OK,
+            Json(ReadinessResponse {
+                status: Readiness::Ready,
+                failed_canaries,
+            }),
+        )
+    } else {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ReadinessResponse {
+                status: Readiness::CanaryFailed,
+                failed_canaries,
+            }),
+        )
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct StatsResponse {
+    #[serde(flatten)]
+    store: StoreStats,
+    uptime_secs: u64,
+}
+
+pub async fn stats(
+    State(state): State<AppState>,
+) -> Result<Json<StatsResponse>, (StatusCode, String)> {
+    let store = state.store()?;
+
+    Ok(Json(StatsResponse {
+        store: store.stats().clone(),
+        uptime_secs: state.started_at.elapsed().as_secs(),
+    }))
+}
@@ -0,0 +1,236 @@
+use phf_shared::{HashKey, PhfHash};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::hash::Hasher;
+use Languages::TargetLanguage;
+
+use crate::normalize::normalize_key;
+use crate::CompressedDictionaryElementWrapper;
+
+/// Read-only (language, key) → entry map. `build_dump` computes a minimal perfect hash over the
+/// normalised keys and stores the entries in slot order, grouped by normalised key. One lookup
+/// finds every capitalisation of a word, lookups take no locks, and each key is stored once, in
+/// its wrapper, which also confirms the hit.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PerfectHashIndex {
+    hash_key: HashKey,
+    disps: Vec<(u32, u32)>,
+    /// Slot `i` holds `elements[offsets[i]..offsets[i + 1]]`, sorted by key.
+    offsets: Vec<u32>,
+    elements: Vec<CompressedDictionaryElementWrapper>,
+}
+
+struct IndexKey<'a> {
+    language_tag: u32,
+    normalized: &'a str,
+}
+
+impl PhfHash for IndexKey<'_> {
+    fn phf_hash<H: Hasher>(&self, state: &mut H) {
+        state.write(&self.language_tag.to_le_bytes());
+        self.normalized.phf_hash(state);
+    }
+}
+
+/// The language's declaration-order index, which is also what bincode writes for it. The derived
+/// `Hash` would feed an `isize`, whose width and byte order depend on the target, so a dump built
+/// on one machine could miss every lookup on another.
+fn language_tag(lang: &TargetLanguage) -> u32 {
+    lang.clone() as u32
+}
+
+impl PerfectHashIndex {
+    pub fn build(elements: Vec<CompressedDictionaryElementWrapper>) -> Self {
+        let mut groups: HashMap<(TargetLanguage, String), Vec<CompressedDictionaryElementWrapper>> =
+            HashMap::new();
+        for element in elements {
+            groups
+                .entry((element.lang.clone(), normalize_key(&element.key)))
+                .or_default()
+                .push(element);
+        }
+        if groups.is_empty() {
+            return Self::default();
+        }
+
+        // The generator's output depends on key order; sorting keeps builds reproducible
+        let mut groups: Vec<_> = groups.into_iter().collect();
+        groups.sort_by(|((a_lang, a), _), ((b_lang, b), _)| {
+            (language_tag(a_lang), a).cmp(&(language_tag(b_lang), b))
+        });
+
+        let state = {
+            let keys: Vec<IndexKey> = groups
+                .iter()
+                .map(|((lang, normalized), _)| IndexKey {
+                    language_tag: language_tag(lang),
+                    normalized,
+                })
+                .collect();
+            phf_generator::generate_hash(&keys)
+        };
+
+        let total = groups.iter().map(|(_, group)| group.len()).sum();
+        let mut slots: Vec<Option<Vec<CompressedDictionaryElementWrapper>>> =
+            groups.into_iter().map(|(_, group)| Some(group)).collect();
+        let mut offsets = Vec::with_capacity(slots.len() + 1);
+        let mut elements = Vec::with_capacity(total);
+        offsets.push(0);
+        for &i in &state.map {
+            let mut group = slots[i].take().expect("perfect hash maps each key once");
+            group.sort_by(|a, b| a.key.cmp(&b.key));
+            elements.extend(group);
+            offsets.push(elements.len() as u32);
+        }
+
+        Self {
+            hash_key: state.key,
+            disps: state.disps,
+            offsets,
+            elements,
+        }
+    }
+
+    pub fn get(
+        &self,
+        lang: &TargetLanguage,
+        key: &str,
+    ) -> Option<&CompressedDictionaryElementWrapper> {
+        // An exact match also proves the slot belongs to `key`
+        self.slot(lang, &normalize_key(key))
+            .iter()
+            .find(|e| e.lang == *lang && e.key == key)
+    }
+
+    /// Every entry whose key normalises like `key`, i.e. all its capitalisations, sorted by key.
+    pub fn variants(
+        &self,
+        lang: &TargetLanguage,
+        key: &str,
+    ) -> &[CompressedDictionaryElementWrapper] {
+        let normalized = normalize_key(key);
+        let group = self.slot(lang, &normalized);
+
+        // Keys outside the set still hash to some slot
+        match group.first() {
+            Some(first) if first.lang == *lang && normalize_key(&first.key) == normalized => group,
+            _ => &[],
+        }
+    }
+
+    /// The entries in the slot `normalized` hashes to, which are its own only if it's in the set.
+    fn slot(
+        &self,
+        lang: &TargetLanguage,
+        normalized: &str,
+    ) -> &[CompressedDictionaryElementWrapper] {
+        if self.elements.is_empty() {
+            return &[];
+        }
+
+        let index_key = IndexKey {
+            language_tag: language_tag(lang),
+            normalized,
+        };
+        let hashes = phf_shared::hash(&index_key, &self.hash_key);
+        let slot = phf_shared::get_index(&hashes, &self.disps, self.offsets.len() - 1) as usize;
+        &self.elements[self.offsets[slot] as usize..self.offsets[slot + 1] as usize]
+    }
+
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &CompressedDictionaryElementWrapper> {
+        self.elements.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wrapper(lang: TargetLanguage, key: &str) -> CompressedDictionaryElementWrapper {
+        CompressedDictionaryElementWrapper {
+            key: key.to_string(),
+            lang,
+            compressed_data: key.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_perfect_hash_lookup() {
+        let index = PerfectHashIndex::build(vec![
+            wrapper(TargetLanguage::German, "Haus"),
+            wrapper(TargetLanguage::German, "haus"),
+            wrapper(TargetLanguage::French, "maison"),
+            wrapper(TargetLanguage::Russian, "дом"),
+        ]);
+
+        assert_eq!(index.len(), 4);
+        for (lang, key) in [
+            (TargetLanguage::German, "Haus"),
+            (TargetLanguage::German, "haus"),
+            (TargetLanguage::French, "maison"),
+            (TargetLanguage::Russian, "дом"),
+        ] {
+            let found = index.get(&lang, key).unwrap();
+            assert_eq!((&found.lang, found.key.as_str()), (&lang, key));
+            assert_eq!(found.compressed_data, key.as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_perfect_hash_variants() {
+        let index = PerfectHashIndex::build(vec![
+            wrapper(TargetLanguage::German, "haus"),
+            wrapper(TargetLanguage::German, "Haus"),
+            wrapper(TargetLanguage::German, "Hof"),
+            wrapper(TargetLanguage::French, "Haus"),
+        ]);
+
+        let keys: Vec<&str> = index
+            .variants(&TargetLanguage::German, "HAUS")
+            .iter()
+            .map(|e| e.key.as_str())
+            .collect();
+        assert_eq!(keys, vec!["Haus", "haus"]);
+        assert!(index.get(&TargetLanguage::German, "HAUS").is_none());
+    }
+
+    #[test]
+    fn test_perfect_hash_misses() {
+        let index = PerfectHashIndex::build(vec![
+            wrapper(TargetLanguage::German, "Haus"),
+            wrapper(TargetLanguage::French, "maison"),
+        ]);
+
+        assert!(index.get(&TargetLanguage::French, "Haus").is_none());
+        assert!(index.get(&TargetLanguage::German, "Hof").is_none());
+        assert!(PerfectHashIndex::default()
+            .get(&TargetLanguage::German, "Haus")
+            .is_none());
+    }
+
+    /// Dumps are built and served on different machines, so the hash must not change with the
+    /// target or the compiler.
+    #[test]
+    fn test_index_hash_is_pinned() {
+        let hashes = phf_shared::hash(
+            &IndexKey {
+                language_tag: 2,
+                normalized: "дом",
+            },
+            &42,
+        );
+
+        assert_eq!(
+            (hashes.g, hashes.f1, hashes.f2),
+            (2873338091, 80471414, 3942636619)
+        );
+    }
+}
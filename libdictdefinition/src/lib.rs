@@ -1,6 +1,14 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use Languages::TargetLanguage;
 
+use index::PerfectHashIndex;
+
+pub mod index;
+pub mod normalize;
+pub mod segment;
+pub mod tokenize;
+
 #[derive(Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
 pub struct Definition {
     pub text: Vec<HyperlinkedText>,
@@ -10,7 +18,179 @@ pub struct Definition {
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
 pub enum HyperlinkedText {
     Plain(String),
-    Link(String),
+    /// `text` is what the gloss says; `target` is the key it resolves to, which may differ by
+    /// stress marks or case ("ука́занный" → "указанный", "The" → "the").
+    Link {
+        text: String,
+        target: String,
+    },
+}
+
+impl HyperlinkedText {
+    /// A link whose text is also its target, as most glosses write it.
+    pub fn link(text: &str) -> Self {
+        HyperlinkedText::Link {
+            text: text.to_string(),
+            target: text.to_string(),
+        }
+    }
+}
+
+/// One entry of wiktextract `sounds`, keeping the regional labels (e.g. "Received-Pronunciation",
+/// "Brazil", "Austria") that `ipa` and `audio` on the element drop.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct Pronunciation {
+    pub ipa: Option<String>,
+    pub ogg_url: Option<String>,
+    pub mp3_url: Option<String>,
+    pub tags: Vec<String>,
+}
+
+/// Grammatical features of an inflected form, drawn from wiktextract `forms` tags and form-of
+/// sense tags so clients can render e.g. "genitive plural of X" the same way for every language.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct GrammaticalAnalysis {
+    pub case: Option<String>,
+    pub number: Option<String>,
+    pub gender: Option<String>,
+    pub person: Option<String>,
+    pub tense: Option<String>,
+    pub mood: Option<String>,
+    pub voice: Option<String>,
+    pub aspect: Option<String>,
+    pub degree: Option<String>,
+    pub other: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct InflectedForm {
+    pub form: String,
+    pub analysis: GrammaticalAnalysis,
+}
+
+const CASE_TAGS: &[&str] = &[
+    "nominative",
+    "genitive",
+    "dative",
+    "accusative",
+    "instrumental",
+    "locative",
+    "prepositional",
+    "vocative",
+    "ablative",
+    "partitive",
+    "essive",
+    "translative",
+    "inessive",
+    "elative",
+    "illative",
+    "adessive",
+    "allative",
+    "comitative",
+];
+const NUMBER_TAGS: &[&str] = &["singular", "plural", "dual"];
+const GENDER_TAGS: &[&str] = &["masculine", "feminine", "neuter", "common"];
+const PERSON_TAGS: &[&str] = &[
+    "first-person",
+    "second-person",
+    "third-person",
+    "impersonal",
+];
+const TENSE_TAGS: &[&str] = &[
+    "present",
+    "past",
+    "future",
+    "preterite",
+    "imperfect",
+    "perfect",
+    "pluperfect",
+    "aorist",
+];
+const MOOD_TAGS: &[&str] = &[
+    "indicative",
+    "subjunctive",
+    "imperative",
+    "conditional",
+    "optative",
+    "jussive",
+];
+const VOICE_TAGS: &[&str] = &["active", "passive", "middle", "reflexive"];
+const ASPECT_TAGS: &[&str] = &["perfective", "imperfective"];
+const DEGREE_TAGS: &[&str] = &["positive", "comparative", "superlative"];
+const OTHER_TAGS: &[&str] = &[
+    "infinitive",
+    "participle",
+    "gerund",
+    "supine",
+    "definite",
+    "indefinite",
+    "strong",
+    "weak",
+    "mixed",
+    "animate",
+    "inanimate",
+    "short-form",
+    "attributive",
+    "predicative",
+];
+
+impl GrammaticalAnalysis {
+    /// Classifies raw (lowercase) wiktextract tags. Returns `None` if none of them describe
+    /// grammar, e.g. only "form-of" or "archaic".
+    pub fn from_tags<S: AsRef<str>>(tags: &[S]) -> Option<Self> {
+        let mut analysis = GrammaticalAnalysis::default();
+        let mut found = false;
+
+        for tag in tags {
+            let tag = tag.as_ref();
+            let slot = if CASE_TAGS.contains(&tag) {
+                &mut analysis.case
+            } else if NUMBER_TAGS.contains(&tag) {
+                &mut analysis.number
+            } else if GENDER_TAGS.contains(&tag) {
+                &mut analysis.gender
+            } else if PERSON_TAGS.contains(&tag) {
+                &mut analysis.person
+            } else if TENSE_TAGS.contains(&tag) {
+                &mut analysis.tense
+            } else if MOOD_TAGS.contains(&tag) {
+                &mut analysis.mood
+            } else if VOICE_TAGS.contains(&tag) {
+                &mut analysis.voice
+            } else if ASPECT_TAGS.contains(&tag) {
+                &mut analysis.aspect
+            } else if DEGREE_TAGS.contains(&tag) {
+                &mut analysis.degree
+            } else {
+                if OTHER_TAGS.contains(&tag) && !analysis.other.iter().any(|t| t == tag) {
+                    analysis.other.push(tag.to_string());
+                    found = true;
+                }
+                continue;
+            };
+
+            // Forms shared between several cases etc. keep the first; the rest are rare enough
+            // that a single label reads better than a list.
+            if slot.is_none() {
+                *slot = Some(tag.to_string());
+            }
+            found = true;
+        }
+
+        if found {
+            Some(analysis)
+        } else {
+            None
+        }
+    }
+}
+
+/// Points a form-of entry at the lemma it was dereferenced to. Stored in the dump instead of a
+/// copy of the lemma; `DictionaryStore::query` fills `resolved_lemma` from it at lookup time.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct LemmaReference {
+    pub lang: TargetLanguage,
+    pub key: String,
 }
 
 // In libdictdefinition.rs
@@ -21,9 +201,19 @@ pub struct DictionaryElementData {
     pub lang: TargetLanguage,
     pub audio: Vec<String>,
     pub ipa: Option<String>,
+    pub pronunciations: Vec<Pronunciation>,
+    /// Latin romanizations, preferred first: wiktextract's own, then our transliteration.
+    pub romanizations: Vec<String>,
     pub word_types: Vec<String>,
     pub definitions: Vec<Definition>,
+    pub analysis: Vec<GrammaticalAnalysis>,
+    /// The inflection table. Empty on forms whose `lemma_reference` points at a lemma with the
+    /// same table; `analysis_for` reads that one instead. `/get_definition` sends `analysis_for`
+    /// rather than the table.
+    pub forms: Vec<InflectedForm>,
     pub dereferenced_text: Option<String>, // Add this line
+    pub lemma_reference: Option<LemmaReference>,
+    pub resolved_lemma: Option<Box<DictionaryElementData>>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -33,7 +223,48 @@ pub struct CompressedDictionaryElementWrapper {
     pub compressed_data: Vec<u8>,
 }
 
+/// Most backlinks the dump keeps per word, and so the most `/backlinks` can return.
+pub const BACKLINK_CAP: usize = 100;
+
+/// Everything `build_dump` writes to `compressed_dict.bin`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DictionaryDump {
+    /// Entries by key; `variants` also finds every capitalisation of a word.
+    pub elements: PerfectHashIndex,
+    /// `normalize::fold_romanization` of each romanization → the native-script keys it names.
+    pub romanizations: HashMap<(TargetLanguage, String), Vec<String>>,
+    /// `normalize::fold_diacritics` of each key that folding changes beyond case → the keys it
+    /// came from.
+    pub folded: HashMap<(TargetLanguage, String), Vec<String>>,
+    /// Key → keys of the entries whose definitions link to it, best first.
+    pub backlinks: HashMap<(TargetLanguage, String), Vec<String>>,
+    /// Stable digest of the entries and every index, identifying the data a server answers from.
+    pub build_id: String,
+    /// Seconds since the Unix epoch when the dump was built.
+    pub built_at: u64,
+}
+
 impl DictionaryElementData {
+    /// An entry for `key` with nothing else filled in; set the rest with struct update syntax.
+    pub fn new(lang: TargetLanguage, key: &str) -> Self {
+        Self {
+            key: key.to_string(),
+            word: key.to_string(),
+            lang,
+            audio: Vec::new(),
+            ipa: None,
+            pronunciations: Vec::new(),
+            romanizations: Vec::new(),
+            word_types: Vec::new(),
+            definitions: Vec::new(),
+            analysis: Vec::new(),
+            forms: Vec::new(),
+            dereferenced_text: None,
+            lemma_reference: None,
+            resolved_lemma: None,
+        }
+    }
+
     pub fn get_wiktionary_link(&self) -> String {
         let encoded_word = self.word.replace(" ", "");
         format!(
@@ -42,4 +273,52 @@ impl DictionaryElementData {
             self.lang.to_wiktionary_long_name_n()
         )
     }
+
+    /// Grammatical analysis of `surface` as a form of this entry. Form-of senses on the entry
+    /// itself win; otherwise the lemma's form table is searched, which covers lookups that
+    /// reached the lemma through case folding or the Czech lemma table.
+    pub fn analysis_for(&self, surface: &str) -> Vec<GrammaticalAnalysis> {
+        if !self.analysis.is_empty() {
+            return self.analysis.clone();
+        }
+
+        let lemma = self.resolved_lemma.as_deref().unwrap_or(self);
+        let surface = surface.to_lowercase();
+        let mut out: Vec<GrammaticalAnalysis> = Vec::new();
+        for form in &lemma.forms {
+            if form.form.to_lowercase() == surface && !out.contains(&form.analysis) {
+                out.push(form.analysis.clone());
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analysis_from_tags() {
+        let analysis = GrammaticalAnalysis::from_tags(&["form-of", "genitive", "plural"]).unwrap();
+        assert_eq!(analysis.case.as_deref(), Some("genitive"));
+        assert_eq!(analysis.number.as_deref(), Some("plural"));
+        assert_eq!(analysis.tense, None);
+        assert!(analysis.other.is_empty());
+    }
+
+    #[test]
+    fn test_analysis_from_tags_past_participle() {
+        let analysis = GrammaticalAnalysis::from_tags(&["past", "participle"]).unwrap();
+        assert_eq!(analysis.tense.as_deref(), Some("past"));
+        assert_eq!(analysis.other, vec!["participle".to_string()]);
+    }
+
+    #[test]
+    fn test_analysis_from_non_grammatical_tags() {
+        assert_eq!(
+            GrammaticalAnalysis::from_tags(&["form-of", "archaic"]),
+            None
+        );
+    }
 }
@@ -0,0 +1,227 @@
+use unicode_normalization::char::{decompose_canonical, is_combining_mark};
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+use Languages::TargetLanguage;
+
+/// Combining acute and grave, the marks textbooks put on stressed Russian vowels, and the
+/// diaeresis of ё, which ordinary Russian text writes as е.
+const RUSSIAN_OPTIONAL_MARKS: &[char] = &['\u{301}', '\u{300}', '\u{308}'];
+
+/// Case- and normalisation-insensitive form of a key: NFC, then lowercase. Every capitalisation
+/// of a word shares one normalised key.
+pub fn normalize_key(text: &str) -> String {
+    // Nearly every key and query is already NFC, and composing is most of the cost
+    if is_nfc_quick(text.chars()) == IsNormalized::Yes {
+        return text.to_lowercase();
+    }
+    text.nfc().flat_map(|c| c.to_lowercase()).collect()
+}
+
+/// "hAUS" → "Haus": the capitalised form proper nouns and German nouns are stored under.
+pub fn lowercase_with_first_uppercase(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        None => String::new(),
+        Some(first) => {
+            first.to_uppercase().collect::<String>() + chars.as_str().to_lowercase().as_str()
+        }
+    }
+}
+
+/// Folds a word for accent-insensitive lookups in `lang`: drops the marks that are optional in
+/// that language, then recomposes and lowercases. Russian loses stress marks and ё becomes е,
+/// but й stays; French loses its accents and cedilla; languages whose accented letters are
+/// letters of their own (German umlauts, Czech háčky and vowel length) only fold NFC/NFD
+/// differences.
+/// "Приве́т" → "привет", "ёлка" → "елка", "Café" → "cafe", "Müll" → "müll".
+pub fn fold_diacritics(lang: &TargetLanguage, text: &str) -> String {
+    let optional_mark: fn(char) -> bool = match lang {
+        TargetLanguage::Russian => |c| RUSSIAN_OPTIONAL_MARKS.contains(&c),
+        TargetLanguage::French => is_combining_mark,
+        _ => |_| false,
+    };
+
+    text.nfd()
+        .filter(|c| !optional_mark(*c))
+        .nfc()
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+/// Folds a romanized word for index lookups: strips diacritics and prime/apostrophe marks used
+/// for soft and hard signs, then lowercases. "privét", "Privet" and "privet" all fold together.
+pub fn fold_romanization(text: &str) -> String {
+    text.nfd()
+        .filter(|c| !is_combining_mark(*c))
+        .filter(|c| !matches!(c, 'ʹ' | 'ʺ' | '\'' | '’' | 'ʼ'))
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+/// Deterministic Latin transliteration of Cyrillic and Greek text. Returns `None` if the text
+/// contains nothing from either script, so Latin-script words don't get a redundant romanization.
+pub fn transliterate(text: &str) -> Option<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut transliterated = false;
+
+    for c in text.nfc() {
+        if is_combining_mark(c) {
+            // Stress marks and other combining diacritics that have no precomposed form.
+            continue;
+        }
+
+        let lower = c.to_lowercase().next().unwrap_or(c);
+        let mapped = transliterate_char(lower).or_else(|| {
+            // Precomposed letters like Greek "ά": map the base letter, drop the accent.
+            let mut base = None;
+            decompose_canonical(lower, |d| {
+                if base.is_none() {
+                    base = Some(d);
+                }
+            });
+            base.filter(|b| *b != lower).and_then(transliterate_char)
+        });
+
+        match mapped {
+            Some(latin) => {
+                out.push_str(latin);
+                transliterated = true;
+            }
+            None => out.push(lower),
+        }
+    }
+
+    if transliterated {
+        Some(out)
+    } else {
+        None
+    }
+}
+
+fn transliterate_char(c: char) -> Option<&'static str> {
+    let latin = match c {
+        // Cyrillic, a Russian-based scheme with the extra Ukrainian and Belarusian letters.
+        'а' => "a",
+        'б' => "b",
+        'в' => "v",
+        'г' => "g",
+        'ґ' => "g",
+        'д' => "d",
+        'е' => "e",
+        'ё' => "yo",
+        'є' => "ye",
+        'ж' => "zh",
+        'з' => "z",
+        'и' => "i",
+        'і' => "i",
+        'ї' => "yi",
+        'й' => "y",
+        'к' => "k",
+        'л' => "l",
+        'м' => "m",
+        'н' => "n",
+        'о' => "o",
+        'п' => "p",
+        'р' => "r",
+        'с' => "s",
+        'т' => "t",
+        'у' => "u",
+        'ў' => "w",
+        'ф' => "f",
+        'х' => "kh",
+        'ц' => "ts",
+        'ч' => "ch",
+        'ш' => "sh",
+        'щ' => "shch",
+        'ъ' => "",
+        'ы' => "y",
+        'ь' => "",
+        'э' => "e",
+        'ю' => "yu",
+        'я' => "ya",
+        // Greek
+        'α' => "a",
+        'β' => "v",
+        'γ' => "g",
+        'δ' => "d",
+        'ε' => "e",
+        'ζ' => "z",
+        'η' => "i",
+        'θ' => "th",
+        'ι' => "i",
+        'κ' => "k",
+        'λ' => "l",
+        'μ' => "m",
+        'ν' => "n",
+        'ξ' => "x",
+        'ο' => "o",
+        'π' => "p",
+        'ρ' => "r",
+        'σ' => "s",
+        'ς' => "s",
+        'τ' => "t",
+        'υ' => "y",
+        'φ' => "f",
+        'χ' => "ch",
+        'ψ' => "ps",
+        'ω' => "o",
+        _ => return None,
+    };
+    Some(latin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_key() {
+        assert_eq!(normalize_key("Haus"), "haus");
+        assert_eq!(normalize_key("c\u{30c}AS"), "\u{10d}as");
+    }
+
+    #[test]
+    fn test_fold_diacritics() {
+        let russian = TargetLanguage::Russian;
+        assert_eq!(fold_diacritics(&russian, "Приве́т"), "привет");
+        assert_eq!(fold_diacritics(&russian, "йо́гурт"), "йогурт");
+        assert_eq!(fold_diacritics(&russian, "Ёлка"), "елка");
+
+        assert_eq!(
+            fold_diacritics(&TargetLanguage::French, "Déjà-vu"),
+            "deja-vu"
+        );
+        assert_eq!(fold_diacritics(&TargetLanguage::French, "garçon"), "garcon");
+
+        assert_eq!(fold_diacritics(&TargetLanguage::German, "Müll"), "müll");
+        // NFD input folds the same as NFC, without losing the letter
+        let czech = TargetLanguage::Czech;
+        assert_eq!(fold_diacritics(&czech, "c\u{30c}as"), "\u{10d}as");
+        assert_eq!(fold_diacritics(&czech, "\u{10d}as"), "\u{10d}as");
+    }
+
+    #[test]
+    fn test_fold_romanization() {
+        assert_eq!(fold_romanization("privét"), "privet");
+        assert_eq!(fold_romanization("dalʹše"), "dalse");
+        assert_eq!(fold_romanization("Kaliméra"), "kalimera");
+    }
+
+    #[test]
+    fn test_transliterate_cyrillic() {
+        assert_eq!(transliterate("приве́т").as_deref(), Some("privet"));
+        assert_eq!(transliterate("Щука").as_deref(), Some("shchuka"));
+        assert_eq!(transliterate("йогурт").as_deref(), Some("yogurt"));
+        assert_eq!(transliterate("дальше").as_deref(), Some("dalshe"));
+    }
+
+    #[test]
+    fn test_transliterate_greek() {
+        assert_eq!(transliterate("καλημέρα").as_deref(), Some("kalimera"));
+        assert_eq!(transliterate("ψυχή").as_deref(), Some("psychi"));
+    }
+
+    #[test]
+    fn test_transliterate_latin_is_none() {
+        assert_eq!(transliterate("Haus"), None);
+    }
+}
@@ -0,0 +1,167 @@
+/// Longest dictionary word the segmenter will consider, in characters.
+pub const MAX_SEGMENT_CHARS: usize = 8;
+
+/// A piece of segmented text. Offsets are byte offsets into the segmented text; `known` is false
+/// for single characters the dictionary had no word for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Segment {
+    pub start: usize,
+    pub end: usize,
+    pub known: bool,
+}
+
+/// Scripts written without spaces between words: Han, kana, Thai, Lao, Khmer and Myanmar.
+pub fn is_scriptio_continua(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}' // Hiragana, Katakana
+        | '\u{31F0}'..='\u{31FF}' // Katakana phonetic extensions
+        | '\u{3400}'..='\u{4DBF}' // CJK extension A
+        | '\u{4E00}'..='\u{9FFF}' // CJK unified ideographs
+        | '\u{F900}'..='\u{FAFF}' // CJK compatibility ideographs
+        | '\u{20000}'..='\u{2FA1F}' // CJK extensions B onwards
+        | '\u{0E00}'..='\u{0E7F}' // Thai
+        | '\u{0E80}'..='\u{0EFF}' // Lao
+        | '\u{1000}'..='\u{109F}' // Myanmar
+        | '\u{1780}'..='\u{17FF}' // Khmer
+    )
+}
+
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+struct Score {
+    unknown_chars: usize,
+    segments: usize,
+    cost: f32,
+}
+
+/// Dictionary-driven maximum matching. `cost` returns `None` for strings that aren't dictionary
+/// words and a unigram cost (lower is more likely) for those that are. The segmentation covering
+/// the most text with known words wins, then the one with fewest segments, then the lowest total
+/// cost; anything left over is split into unknown single characters.
+pub fn segment(text: &str, cost: impl Fn(&str) -> Option<f32>) -> Vec<Segment> {
+    let boundaries: Vec<usize> = text
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain(std::iter::once(text.len()))
+        .collect();
+    let n = boundaries.len() - 1;
+
+    // best[i]: score of the best segmentation of the first i characters, and where its last
+    // segment starts
+    let mut best: Vec<Option<(Score, usize, bool)>> = vec![None; n + 1];
+    best[0] = Some((
+        Score {
+            unknown_chars: 0,
+            segments: 0,
+            cost: 0.0,
+        },
+        0,
+        true,
+    ));
+
+    for end in 1..=n {
+        for len in 1..=MAX_SEGMENT_CHARS.min(end) {
+            let start = end - len;
+            let prev = match best[start] {
+                Some((score, _, _)) => score,
+                None => continue,
+            };
+
+            let word = &text[boundaries[start]..boundaries[end]];
+            let (score, known) = match cost(word) {
+                Some(c) => (
+                    Score {
+                        unknown_chars: prev.unknown_chars,
+                        segments: prev.segments + 1,
+                        cost: prev.cost + c,
+                    },
+                    true,
+                ),
+                None if len == 1 => (
+                    Score {
+                        unknown_chars: prev.unknown_chars + 1,
+                        segments: prev.segments + 1,
+                        cost: prev.cost,
+                    },
+                    false,
+                ),
+                None => continue,
+            };
+
+            let better = match best[end] {
+                Some((current, _, _)) => score < current,
+                None => true,
+            };
+            if better {
+                best[end] = Some((score, start, known));
+            }
+        }
+    }
+
+    let mut segments = Vec::new();
+    let mut end = n;
+    while end > 0 {
+        let (_, start, known) = best[end].expect("every prefix is reachable by single chars");
+        segments.push(Segment {
+            start: boundaries[start],
+            end: boundaries[end],
+            known,
+        });
+        end = start;
+    }
+    segments.reverse();
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dictionary<'a>(words: &'a [(&'a str, f32)]) -> impl Fn(&str) -> Option<f32> + 'a {
+        move |w| words.iter().find(|(word, _)| *word == w).map(|(_, c)| *c)
+    }
+
+    fn texts<'a>(text: &'a str, segments: &[Segment]) -> Vec<&'a str> {
+        segments.iter().map(|s| &text[s.start..s.end]).collect()
+    }
+
+    #[test]
+    fn test_segment_prefers_fewest_words() {
+        let text = "我是学生";
+        let words = [
+            ("我", 1.0),
+            ("是", 1.0),
+            ("学", 1.0),
+            ("生", 1.0),
+            ("学生", 1.0),
+        ];
+        let segments = segment(text, dictionary(&words));
+        assert_eq!(texts(text, &segments), vec!["我", "是", "学生"]);
+        assert!(segments.iter().all(|s| s.known));
+    }
+
+    #[test]
+    fn test_segment_cost_breaks_ties() {
+        // Both "研究/生命" and "研究生/命" are two words; the cheaper one wins.
+        let text = "研究生命";
+        let words = [("研究", 1.0), ("生命", 1.0), ("研究生", 2.0), ("命", 3.0)];
+        let segments = segment(text, dictionary(&words));
+        assert_eq!(texts(text, &segments), vec!["研究", "生命"]);
+    }
+
+    #[test]
+    fn test_segment_unknown_characters() {
+        let text = "猫x";
+        let segments = segment(text, dictionary(&[]));
+        assert_eq!(texts(text, &segments), vec!["猫", "x"]);
+        assert!(segments.iter().all(|s| !s.known));
+    }
+
+    #[test]
+    fn test_is_scriptio_continua() {
+        assert!(is_scriptio_continua('学'));
+        assert!(is_scriptio_continua('ひ'));
+        assert!(is_scriptio_continua('ก'));
+        assert!(!is_scriptio_continua('a'));
+        assert!(!is_scriptio_continua('я'));
+    }
+}
@@ -0,0 +1,83 @@
+/// A run of either word characters or filler (whitespace, digits, punctuation). Offsets are
+/// byte offsets into the tokenized text; consecutive tokens always cover it without gaps.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub text: &'a str,
+    pub start: usize,
+    pub end: usize,
+    pub is_word: bool,
+}
+
+pub fn is_non_content(c: &char) -> bool {
+    let also_prohibited = [
+        '!', '"', '£', '$', '%', '^', '&', '*', '(', ')', '-', '_', '=', '+', '[', ']', ':', ';',
+        '\'', '~', '@', '#', '<', ',', '.', '>', '/', '?', '\\', '|',
+    ];
+    c.is_whitespace() || c.is_numeric() || also_prohibited.contains(c)
+}
+
+/// Splits text the way gloss hyperlinking does: words are maximal runs of content characters,
+/// and everything between them is kept as filler.
+pub fn tokenize(text: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut current_is_word = None;
+
+    for (i, c) in text.char_indices() {
+        let is_word = !is_non_content(&c);
+        match current_is_word {
+            Some(current) if current != is_word => {
+                tokens.push(Token {
+                    text: &text[start..i],
+                    start,
+                    end: i,
+                    is_word: current,
+                });
+                start = i;
+            }
+            _ => {}
+        }
+        current_is_word = Some(is_word);
+    }
+
+    if let Some(is_word) = current_is_word {
+        tokens.push(Token {
+            text: &text[start..],
+            start,
+            end: text.len(),
+            is_word,
+        });
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize_words_and_filler() {
+        let tokens = tokenize("kick the bucket, 2 times");
+        let texts: Vec<(&str, bool)> = tokens.iter().map(|t| (t.text, t.is_word)).collect();
+        assert_eq!(
+            texts,
+            vec![
+                ("kick", true),
+                (" ", false),
+                ("the", true),
+                (" ", false),
+                ("bucket", true),
+                (", 2 ", false),
+                ("times", true),
+            ]
+        );
+        assert_eq!(tokens[4].start, 9);
+        assert_eq!(tokens[4].end, 15);
+    }
+
+    #[test]
+    fn test_tokenize_empty() {
+        assert!(tokenize("").is_empty());
+    }
+}